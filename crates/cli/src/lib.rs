@@ -0,0 +1,238 @@
+//! the `oh-no` shell: statements and meta-commands in, text to print out
+mod output;
+
+use engine::script::Session;
+use engine::storage::Storage;
+use engine::EngineError;
+use engine_core::{
+    parse_script, render_parse_error, render_type_errors, script_is_complete, TableName,
+};
+
+const HELP: &str = ".tables          list every table
+.schema [Table]  print the definition of one table, or all of them
+.mode table      print rows as a table
+.mode json       print results as JSON
+.help            show this message
+.quit            leave";
+
+/// how results are printed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Table,
+    Json,
+}
+
+pub struct Shell<'a, S> {
+    pub mode: Mode,
+    db: &'a S,
+    /// keeps a transaction open from `begin` until `commit`, across inputs
+    session: Session<'a, S>,
+}
+
+impl<'a, S: Storage> Shell<'a, S> {
+    pub fn new(db: &'a S) -> Self {
+        Shell {
+            mode: Mode::default(),
+            db,
+            session: Session::new(db),
+        }
+    }
+
+    /// whether a transaction has begun and is waiting for `commit` or `rollback`
+    pub fn in_transaction(&self) -> bool {
+        self.session.in_transaction()
+    }
+
+    /// run a meta-command, or every statement in the input until one fails,
+    /// returning what to print
+    pub fn run(&mut self, input: &str) -> String {
+        match input.trim().strip_prefix('.') {
+            Some(command) => self.meta_command(command),
+            None => self.statements(input),
+        }
+    }
+
+    fn meta_command(&mut self, command: &str) -> String {
+        let db = self.db;
+        let mut words = command.split_whitespace();
+        let result = match (words.next(), words.next(), words.next()) {
+            (Some("tables"), None, _) => {
+                engine::catalog::list_tables(db).map(|tables| output::tables(self.mode, &tables))
+            }
+            (Some("schema"), None, _) => schemas(db),
+            (Some("schema"), Some(table), None) => {
+                engine::catalog::describe_table(db, &TableName(table.to_string()))
+                    .map(|table| table.to_string())
+            }
+            (Some("mode"), Some("table"), None) => {
+                self.mode = Mode::Table;
+                Ok(String::new())
+            }
+            (Some("mode"), Some("json"), None) => {
+                self.mode = Mode::Json;
+                Ok(String::new())
+            }
+            (Some("help"), None, _) => Ok(HELP.to_string()),
+            _ => Ok(format!("unknown command `.{command}`, try `.help`")),
+        };
+
+        result.unwrap_or_else(|error| format!("error: {error}"))
+    }
+
+    fn statements(&mut self, source: &str) -> String {
+        let statements = match parse_script(source) {
+            Ok(statements) => statements,
+            Err(error) => return render_parse_error(source, &error),
+        };
+
+        let mut printed = vec![];
+        for (span, statement, spans) in statements {
+            match self.session.execute(statement) {
+                Ok(result) => printed.push(output::output(self.mode, &result)),
+                Err(error) => {
+                    printed.push(match error.type_errors() {
+                        Some(errors) => render_type_errors(source, span, &spans, errors),
+                        None => format!("error: {error}"),
+                    });
+                    break;
+                }
+            }
+        }
+        printed.join("\n")
+    }
+}
+
+/// whether the input can be run yet: a meta-command, or statements ending in `;`
+/// with nothing but whitespace and comments after it
+pub fn is_complete(input: &str) -> bool {
+    input.trim().starts_with('.') || script_is_complete(input)
+}
+
+// every table, as the statements that would create them
+fn schemas(db: &impl Storage) -> Result<String, EngineError> {
+    engine::catalog::list_tables(db)?
+        .iter()
+        .map(|table| engine::catalog::describe_table(db, table).map(|table| table.to_string()))
+        .collect::<Result<Vec<_>, _>>()
+        .map(|tables| tables.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_complete, Mode, Shell};
+    use engine::storage::MemoryStorage;
+
+    #[test]
+    fn test_statements() {
+        let db = MemoryStorage::default();
+        let mut shell = Shell::new(&db);
+
+        assert_eq!(
+            shell.run(
+                "type User { name: String, age: Int };
+                insert into User key 1 { name: \"Egg\", age: 27 } returning key;
+                insert into User key 2 { name: \"Horse\", age: 3 };"
+            ),
+            "table created\ninserted key 1\n1 row affected"
+        );
+        assert_eq!(
+            shell.run("select name, age from User;"),
+            " name  | age
+-------+-----
+ Egg   | 27
+ Horse | 3
+(2 rows)"
+        );
+        assert_eq!(
+            shell.run("delete from User where age = 3;"),
+            "1 row affected"
+        );
+
+        shell.run(".mode json");
+        assert_eq!(shell.mode, Mode::Json);
+        assert_eq!(shell.run("select age from User;"), "[{\"age\":27}]");
+        assert_eq!(
+            shell.run("insert into User key 3 { name: \"Log\", age: 1 };"),
+            "{\"key\":3}"
+        );
+    }
+
+    #[test]
+    fn test_meta_commands() {
+        let db = MemoryStorage::default();
+        let mut shell = Shell::new(&db);
+
+        assert_eq!(shell.run(".tables"), "no tables");
+        shell.run("type User { name: String }; type Pet { age: Int };");
+
+        assert_eq!(shell.run(".tables"), "Pet\nUser");
+        assert_eq!(shell.run(".schema User"), "type User { name: String }");
+        assert_eq!(
+            shell.run(".schema"),
+            "type Pet { age: Int }\ntype User { name: String }"
+        );
+        assert_eq!(
+            shell.run(".schema Horse"),
+            "error: table not found: Horse"
+        );
+        assert_eq!(
+            shell.run(".horse"),
+            "unknown command `.horse`, try `.help`"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let db = MemoryStorage::default();
+        let mut shell = Shell::new(&db);
+        shell.run("type User { name: String };");
+
+        assert_eq!(
+            shell.run("select name User;"),
+            "1 | select name User;\n  |             ^^^^ expected `from` after column list"
+        );
+        assert_eq!(
+            shell.run("select age from User;"),
+            "1 | select age from User;\n  |        ^^^ column age not found in table User"
+        );
+        assert_eq!(
+            shell.run("select name\nfrom User\nwhere age = 1;"),
+            "3 | where age = 1;\n  |       ^^^^^^^ column age not found in table User"
+        );
+    }
+
+    #[test]
+    fn test_transaction() {
+        let db = MemoryStorage::default();
+        let mut shell = Shell::new(&db);
+        shell.run("type User { name: String };");
+
+        assert_eq!(shell.run("begin;"), "begin");
+        assert_eq!(
+            shell.run("insert into User key 1 { name: \"Egg\" };"),
+            "1 row affected"
+        );
+        assert!(shell.in_transaction());
+        assert_eq!(shell.run("rollback;"), "rollback");
+        assert!(!shell.in_transaction());
+
+        assert_eq!(
+            shell.run("select name from User;"),
+            " name\n------\n(0 rows)"
+        );
+        assert_eq!(
+            shell.run("commit;"),
+            "error: `commit` without a `begin` before it"
+        );
+    }
+
+    #[test]
+    fn test_is_complete() {
+        assert!(is_complete(".tables"));
+        assert!(is_complete("select name from User;  "));
+        assert!(is_complete("select name from User; -- note"));
+        assert!(!is_complete("select name\nfrom User"));
+        assert!(!is_complete("select name from User -- not yet;"));
+    }
+}
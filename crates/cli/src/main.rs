@@ -0,0 +1,108 @@
+//! `oh-no [--json] <path>`
+//!
+//! an interactive shell over the database at `path`, which is created if it
+//! does not exist. statements run once a line ends in `;`, and lines starting
+//! with `.` are meta-commands, see `.help`
+use oh_no::{is_complete, Mode, Shell};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: oh-no [--json] <path>";
+
+fn main() -> ExitCode {
+    let mut mode = Mode::default();
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => mode = Mode::Json,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    match run(mode, &path) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(mode: Mode, path: &str) -> Result<(), String> {
+    let db = rocksdb::DB::open_default(path).map_err(|error| format!("{path}: {error}"))?;
+    let mut shell = Shell::new(&db);
+    shell.mode = mode;
+    let mut editor = DefaultEditor::new().map_err(|error| error.to_string())?;
+
+    let history = history_path();
+    if let Some(history) = &history {
+        // there is no history the first time
+        let _ = editor.load_history(history);
+    }
+
+    let mut input = String::new();
+    loop {
+        // a `*` while there is a transaction waiting to be committed
+        let prompt = match (input.is_empty(), shell.in_transaction()) {
+            (true, false) => "oh-no> ",
+            (true, true) => "oh-no*> ",
+            (false, _) => "  ...> ",
+        };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                input.push_str(&line);
+                input.push('\n');
+            }
+            // ctrl-c throws away whatever has been typed so far
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(error.to_string()),
+        }
+
+        if !is_complete(&input) {
+            continue;
+        }
+        let entry = input.trim().to_string();
+        input.clear();
+        if entry.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(entry.as_str());
+
+        if entry == ".quit" || entry == ".exit" {
+            break;
+        }
+        let printed = shell.run(&entry);
+        if !printed.is_empty() {
+            println!("{printed}");
+        }
+    }
+
+    if let Some(history) = &history {
+        editor
+            .save_history(history)
+            .map_err(|error| format!("{}: {error}", history.display()))?;
+    }
+    Ok(())
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".oh_no_history"))
+}
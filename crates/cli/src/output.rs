@@ -0,0 +1,100 @@
+//! printing what statements produced, as text or as JSON
+use crate::Mode;
+use engine::script::Output;
+use engine::select::ResultSet;
+use engine_core::{ScalarValue, TableName};
+use serde_json::{json, Value};
+
+pub(crate) fn output(mode: Mode, output: &Output) -> String {
+    match (mode, output) {
+        (_, Output::Tables(tables)) => self::tables(mode, tables),
+        (Mode::Table, Output::TableCreated) => "table created".to_string(),
+        (Mode::Table, Output::Rows(result)) => table(result),
+        (Mode::Table, Output::Inserted(key)) => format!("inserted key {key}"),
+        (Mode::Table, Output::RowsAffected(1)) => "1 row affected".to_string(),
+        (Mode::Table, Output::RowsAffected(count)) => format!("{count} rows affected"),
+        (Mode::Table, Output::Table(table)) => table.to_string(),
+        (Mode::Table, Output::Transaction(command)) => command.to_string(),
+        (Mode::Json, Output::TableCreated) => json!({ "table_created": true }).to_string(),
+        (Mode::Json, Output::Rows(result)) => Value::Array(result.to_json()).to_string(),
+        (Mode::Json, Output::Inserted(key)) => json!({ "key": key }).to_string(),
+        (Mode::Json, Output::RowsAffected(count)) => json!({ "rows_affected": count }).to_string(),
+        (Mode::Json, Output::Table(table)) => json!(table).to_string(),
+        (Mode::Json, Output::Transaction(command)) => {
+            json!({ "transaction": command.to_string() }).to_string()
+        }
+    }
+}
+
+pub(crate) fn tables(mode: Mode, tables: &[TableName]) -> String {
+    let names = tables.iter().map(ToString::to_string);
+    match mode {
+        Mode::Table if tables.is_empty() => "no tables".to_string(),
+        Mode::Table => names.collect::<Vec<_>>().join("\n"),
+        Mode::Json => json!(names.collect::<Vec<_>>()).to_string(),
+    }
+}
+
+// rows lined up under their column names
+fn table(result: &ResultSet) -> String {
+    let columns: Vec<&str> = result
+        .schema
+        .iter()
+        .map(|(column, _)| column.0.as_str())
+        .collect();
+    let cells: Vec<Vec<String>> = result
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| match value {
+                    ScalarValue::String(string) => string.clone(),
+                    ScalarValue::Null => String::new(),
+                    value => value.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            cells
+                .iter()
+                .map(|row| row[index].chars().count())
+                .chain([column.chars().count()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let line = |cells: &mut dyn Iterator<Item = &str>| {
+        cells
+            .zip(&widths)
+            .map(|(cell, width)| format!(" {cell:width$} "))
+            .collect::<Vec<_>>()
+            .join("|")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut lines = vec![line(&mut columns.iter().copied())];
+    lines.push(
+        widths
+            .iter()
+            .map(|width| "-".repeat(width + 2))
+            .collect::<Vec<_>>()
+            .join("+"),
+    );
+    lines.extend(
+        cells
+            .iter()
+            .map(|row| line(&mut row.iter().map(String::as_str))),
+    );
+    lines.push(match result.rows.len() {
+        1 => "(1 row)".to_string(),
+        count => format!("({count} rows)"),
+    });
+    lines.join("\n")
+}
@@ -0,0 +1,220 @@
+//! printing errors underneath the part of the statement that caused them
+use crate::types::{Location, ParseError, SourceMap, Span, TypeErrors};
+use nom::error::{VerboseError, VerboseErrorKind};
+use nom::Offset;
+
+/// the line of `source` containing `span`, with carets under the span and
+/// `message` after them
+///
+/// ```text
+/// 1 | select id name from User
+///   |           ^^^^ expected `from` after column list
+/// ```
+pub fn render_diagnostic(source: &str, span: Span, message: &str) -> String {
+    let start = span.start.min(source.len());
+    let line_start = source[..start].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |index| start + index);
+    let line_number = (source[..line_start].matches('\n').count() + 1).to_string();
+
+    let indent = source[line_start..start].chars().count();
+    let width = source[start..span.end.clamp(start, line_end)]
+        .chars()
+        .count()
+        .max(1);
+
+    format!(
+        "{line_number} | {line}\n{gutter} | {indent}{carets} {message}",
+        line = &source[line_start..line_end],
+        gutter = " ".repeat(line_number.len()),
+        indent = " ".repeat(indent),
+        carets = "^".repeat(width),
+    )
+}
+
+/// the parse error and the line it happened on
+pub fn render_parse_error(source: &str, error: &ParseError) -> String {
+    render_diagnostic(source, error.span, &error.message)
+}
+
+/// turn a nom error from parsing `source` into something that says what was expected
+pub(crate) fn parse_error(source: &str, error: &nom::Err<VerboseError<&str>>) -> ParseError {
+    let end = Span {
+        start: source.len(),
+        end: source.len(),
+    };
+    let errors = match error {
+        nom::Err::Incomplete(_) => {
+            return ParseError {
+                span: end,
+                message: "unexpected end of input".to_string(),
+            }
+        }
+        nom::Err::Error(error) | nom::Err::Failure(error) => &error.errors,
+    };
+
+    // the innermost error is where parsing actually stopped, and the innermost
+    // context is the most specific thing we were trying to parse
+    let span = errors
+        .first()
+        .map_or(end, |(input, _)| token_span(source, input));
+    let message = errors
+        .iter()
+        .find_map(|(_, kind)| match kind {
+            VerboseErrorKind::Context(context) => Some((*context).to_string()),
+            _ => None,
+        })
+        .unwrap_or_else(|| "could not parse this".to_string());
+
+    let message = if span.start == source.len() {
+        format!("{message}, but the statement ended")
+    } else {
+        message
+    };
+    ParseError { span, message }
+}
+
+// the word, number or symbol at the start of `rest`
+pub(crate) fn token_span(source: &str, rest: &str) -> Span {
+    let start = source.offset(rest);
+    let word = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    let length = match word {
+        0 => rest.chars().next().map_or(0, char::len_utf8),
+        word => word,
+    };
+    Span {
+        start,
+        end: start + length,
+    }
+}
+
+/// every type error in the statement at `statement` in `source`, each pointing
+/// at the part of the statement it is about. `spans` is what `parse_script`
+/// found for that statement
+pub fn render_type_errors(
+    source: &str,
+    statement: Span,
+    spans: &SourceMap,
+    errors: &TypeErrors,
+) -> String {
+    errors
+        .0
+        .iter()
+        .map(|(location, error)| {
+            // columns that are missing from an insert are not in the source at all
+            let span = spans
+                .span(location)
+                .or_else(|| match location {
+                    Location::Column(_) => spans.span(&Location::Values),
+                    _ => None,
+                })
+                .unwrap_or(statement);
+            render_diagnostic(source, span, &error.to_string())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_parse_error, render_type_errors};
+    use crate::{
+        parse_script, parse_statement, typecheck_delete, typecheck_insert, ColumnName, Columns,
+        Defaults, ScalarType, Statement, Table, TableName,
+    };
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_parse_error() {
+        let source = "select id name from User";
+        let error = parse_statement(source).expect_err("parse_statement");
+
+        assert_eq!(
+            render_parse_error(source, &error),
+            "1 | select id name from User\n  |           ^^^^ expected `from` after column list"
+        );
+    }
+
+    #[test]
+    fn test_parse_error_at_end() {
+        let source = "select id from User\nwhere";
+        let error = parse_statement(source).expect_err("parse_statement");
+
+        assert_eq!(
+            render_parse_error(source, &error),
+            "2 | where\n  |      ^ expected an expression after `where`, but the statement ended"
+        );
+    }
+
+    #[test]
+    fn test_type_errors() {
+        let mut columns = BTreeMap::new();
+        columns.insert(ColumnName("age".to_string()), ScalarType::Int);
+        columns.insert(ColumnName("name".to_string()), ScalarType::String);
+
+        let mut tables = BTreeMap::new();
+        tables.insert(
+            TableName("User".to_string()),
+            Table {
+                name: TableName("User".to_string()),
+                columns: Columns::SingleConstructor(columns),
+                auto_increment: false,
+                defaults: Defaults::default(),
+            },
+        );
+
+        let source = "show tables;\ninsert into User key 1 { age: \"old\" }";
+        let (span, statement, spans) = parse_script(source).expect("parse_script").remove(1);
+        let Statement::Insert(insert) = statement else {
+            panic!("expected an insert")
+        };
+        let errors = typecheck_insert(&tables, &insert).expect_err("typecheck_insert");
+
+        assert_eq!(
+            render_type_errors(source, span, &spans, &errors),
+            [
+                "2 | insert into User key 1 { age: \"old\" }",
+                "  |                          ^^^^^^^^^^ expected type Int but found value \"old\"",
+                "2 | insert into User key 1 { age: \"old\" }",
+                "  |                        ^^^^^^^^^^^^^^ missing column name when inserting into table User",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_type_error_in_where() {
+        let mut columns = BTreeMap::new();
+        columns.insert(ColumnName("age".to_string()), ScalarType::Int);
+
+        let mut tables = BTreeMap::new();
+        tables.insert(
+            TableName("User".to_string()),
+            Table {
+                name: TableName("User".to_string()),
+                columns: Columns::SingleConstructor(columns),
+                auto_increment: false,
+                defaults: Defaults::default(),
+            },
+        );
+
+        let source = "delete from User where age = 1 && name = \"Egg\"";
+        let (span, statement, spans) = parse_script(source).expect("parse_script").remove(0);
+        let Statement::Delete(delete) = statement else {
+            panic!("expected a delete")
+        };
+        let errors = typecheck_delete(&tables, &delete).expect_err("typecheck_delete");
+
+        assert_eq!(
+            render_type_errors(source, span, &spans, &errors),
+            [
+                "1 | delete from User where age = 1 && name = \"Egg\"",
+                "  |                                   ^^^^^^^^^^^^ column name not found in table User",
+            ]
+            .join("\n")
+        );
+    }
+}
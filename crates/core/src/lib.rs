@@ -1,14 +1,27 @@
+mod diagnostic;
 mod parser;
+mod pretty;
 mod typecheck;
 mod types;
 
-pub use parser::{parse_select, parse_table};
+pub use diagnostic::{render_diagnostic, render_parse_error, render_type_errors};
+pub use parser::{
+    parse_alter, parse_catalog, parse_delete, parse_insert, parse_script, parse_select,
+    parse_statement, parse_table, parse_transaction, parse_update, script_is_complete,
+};
+pub use pretty::format_statement;
 pub use typecheck::{
+    alter::typecheck_alter,
+    delete::typecheck_delete,
     insert::typecheck_insert,
     select::{empty_where, typecheck_select},
+    table::typecheck_table,
+    update::typecheck_update,
 };
 pub use types::{
-    and, bool_expr, equals, ColumnName, Columns, Comparison, Constructor, Expression, Function,
-    Insert, InsertError, InsertValue, ScalarType, ScalarValue, Select, SelectColumns, SelectError,
-    Table, TableName, TypeError,
+    and, bool_expr, equals, AlterError, AlterTable, CatalogCommand, CatalogError, ColumnName,
+    Columns, Comparison, Constructor, Defaults, Delete, DeleteError, Expression, Function, Insert,
+    InsertError, InsertValue, Location, OnConflict, ParseError, ScalarType, ScalarValue, Select,
+    SelectColumns, SelectError, SourceMap, Span, Statement, Table, TableChange, TableName,
+    TransactionCommand, Type, TypeError, TypeErrors, Update, UpdateError, MAX_CONSTRUCTORS,
 };
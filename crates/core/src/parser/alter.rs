@@ -0,0 +1,192 @@
+use super::expression::scalar_value;
+use super::identifiers::{column_name, constructor, keyword, table_name, ws};
+use super::spans::{spanned, Spans};
+use super::table::{scalar_type, single_constructor};
+use super::IResult;
+use crate::types::{AlterTable, Location, TableChange};
+
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{cut, map},
+    error::context,
+    sequence::{pair, preceded, separated_pair, tuple},
+};
+
+// `alter type User add column email: String default ""`
+pub fn parse_alter(input: &str) -> IResult<&str, AlterTable> {
+    map(alter_with_spans, |(alter, _)| alter)(input)
+}
+
+pub fn alter_with_spans(input: &str) -> IResult<&str, (AlterTable, Spans<'_>)> {
+    map(
+        pair(
+            preceded(
+                pair(
+                    keyword("alter"),
+                    cut(context("expected `type` after `alter`", keyword("type"))),
+                ),
+                cut(context(
+                    "expected a table name after `type`",
+                    spanned(table_name),
+                )),
+            ),
+            cut(context(
+                "expected `add column`, `drop column`, `rename column` or `add constructor`",
+                table_change,
+            )),
+        ),
+        |((table, table_text), (change, mut spans))| {
+            spans.insert(0, (Location::Table, table_text));
+            (AlterTable { table, change }, spans)
+        },
+    )(input)
+}
+
+fn table_change(input: &str) -> IResult<&str, (TableChange, Spans<'_>)> {
+    let add_column = map(
+        tuple((
+            preceded(
+                pair(keyword("add"), keyword("column")),
+                spanned(column_name),
+            ),
+            preceded(
+                cut(context("expected `:` after column name", ws(tag(":")))),
+                cut(scalar_type),
+            ),
+            preceded(
+                cut(context(
+                    "expected `default` after column type",
+                    keyword("default"),
+                )),
+                cut(context("expected a value after `default`", scalar_value)),
+            ),
+        )),
+        |((column, text), scalar_type, default)| {
+            let spans = vec![(Location::Column(column.clone()), text)];
+            let change = TableChange::AddColumn {
+                column,
+                scalar_type,
+                default,
+            };
+            (change, spans)
+        },
+    );
+    let drop_column = map(
+        preceded(
+            pair(keyword("drop"), keyword("column")),
+            spanned(column_name),
+        ),
+        |(column, text)| {
+            let spans = vec![(Location::Column(column.clone()), text)];
+            (TableChange::DropColumn { column }, spans)
+        },
+    );
+    let rename_column = map(
+        preceded(
+            pair(keyword("rename"), keyword("column")),
+            separated_pair(
+                spanned(column_name),
+                cut(context("expected `to` after column name", keyword("to"))),
+                spanned(column_name),
+            ),
+        ),
+        |((from, from_text), (to, to_text))| {
+            let spans = vec![
+                (Location::Column(from.clone()), from_text),
+                (Location::Column(to.clone()), to_text),
+            ];
+            (TableChange::RenameColumn { from, to }, spans)
+        },
+    );
+    let add_constructor = map(
+        preceded(
+            pair(keyword("add"), keyword("constructor")),
+            pair(spanned(constructor), single_constructor),
+        ),
+        |((constructor, constructor_text), (columns, defaults, mut spans))| {
+            spans.insert(0, (Location::Constructor, constructor_text));
+            let change = TableChange::AddConstructor {
+                constructor,
+                columns,
+                defaults,
+            };
+            (change, spans)
+        },
+    );
+
+    alt((add_column, drop_column, rename_column, add_constructor))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_alter;
+    use crate::{
+        AlterTable, ColumnName, Constructor, ScalarType, ScalarValue, TableChange, TableName,
+    };
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_alter_columns() {
+        assert_eq!(
+            parse_alter("alter type User add column email: String default \"\""),
+            Ok((
+                "",
+                AlterTable {
+                    table: TableName("User".to_string()),
+                    change: TableChange::AddColumn {
+                        column: ColumnName("email".to_string()),
+                        scalar_type: ScalarType::String,
+                        default: ScalarValue::String(String::new())
+                    }
+                }
+            ))
+        );
+        assert_eq!(
+            parse_alter("alter type User drop column email"),
+            Ok((
+                "",
+                AlterTable {
+                    table: TableName("User".to_string()),
+                    change: TableChange::DropColumn {
+                        column: ColumnName("email".to_string())
+                    }
+                }
+            ))
+        );
+        assert_eq!(
+            parse_alter("alter type User rename column email to contact"),
+            Ok((
+                "",
+                AlterTable {
+                    table: TableName("User".to_string()),
+                    change: TableChange::RenameColumn {
+                        from: ColumnName("email".to_string()),
+                        to: ColumnName("contact".to_string())
+                    }
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_add_constructor() {
+        let mut columns = BTreeMap::new();
+        columns.insert(ColumnName("fins".to_string()), ScalarType::Int);
+
+        assert_eq!(
+            parse_alter("alter type Pet add constructor Fish { fins: Int }"),
+            Ok((
+                "",
+                AlterTable {
+                    table: TableName("Pet".to_string()),
+                    change: TableChange::AddConstructor {
+                        constructor: Constructor("Fish".to_string()),
+                        columns,
+                        defaults: BTreeMap::new()
+                    }
+                }
+            ))
+        );
+    }
+}
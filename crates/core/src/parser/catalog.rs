@@ -0,0 +1,58 @@
+use super::identifiers::{keyword, table_name};
+use super::IResult;
+use crate::types::CatalogCommand;
+
+use nom::{
+    branch::alt,
+    combinator::map,
+    sequence::{pair, preceded},
+};
+
+// `show tables`, `describe User` or `drop type User`
+pub fn parse_catalog(input: &str) -> IResult<&str, CatalogCommand> {
+    let show_tables = map(pair(keyword("show"), keyword("tables")), |_| {
+        CatalogCommand::ShowTables
+    });
+    let describe = map(preceded(keyword("describe"), table_name), |table| {
+        CatalogCommand::Describe { table }
+    });
+    let drop_table = map(
+        preceded(pair(keyword("drop"), keyword("type")), table_name),
+        |table| CatalogCommand::DropTable { table },
+    );
+
+    alt((show_tables, describe, drop_table))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_catalog;
+    use crate::{CatalogCommand, TableName};
+
+    #[test]
+    fn test_catalog() {
+        assert_eq!(
+            parse_catalog("show tables"),
+            Ok(("", CatalogCommand::ShowTables))
+        );
+        assert_eq!(
+            parse_catalog("describe User"),
+            Ok((
+                "",
+                CatalogCommand::Describe {
+                    table: TableName("User".to_string())
+                }
+            ))
+        );
+        assert_eq!(
+            parse_catalog("drop type User"),
+            Ok((
+                "",
+                CatalogCommand::DropTable {
+                    table: TableName("User".to_string())
+                }
+            ))
+        );
+        assert!(parse_catalog("drop User").is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use super::identifiers::{keyword, table_name};
+use super::select::r#where;
+use super::spans::{spanned, Spans};
+use super::IResult;
+use crate::types::{Delete, Location};
+
+use nom::{
+    combinator::{cut, map},
+    error::context,
+    sequence::{pair, preceded},
+};
+
+// `delete from User where age = 27`
+pub fn parse_delete(input: &str) -> IResult<&str, Delete> {
+    map(delete_with_spans, |(delete, _)| delete)(input)
+}
+
+pub fn delete_with_spans(input: &str) -> IResult<&str, (Delete, Spans<'_>)> {
+    map(
+        pair(
+            preceded(
+                pair(
+                    keyword("delete"),
+                    cut(context("expected `from` after `delete`", keyword("from"))),
+                ),
+                cut(context(
+                    "expected a table name after `from`",
+                    spanned(table_name),
+                )),
+            ),
+            r#where,
+        ),
+        |((table, table_text), (r#where, mut spans))| {
+            spans.insert(0, (Location::Table, table_text));
+            (Delete { table, r#where }, spans)
+        },
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_delete;
+    use crate::{empty_where, equals, ColumnName, Delete, ScalarValue, TableName};
+
+    #[test]
+    fn test_delete() {
+        assert_eq!(
+            parse_delete("delete from User"),
+            Ok((
+                "",
+                Delete {
+                    table: TableName("User".to_string()),
+                    r#where: empty_where()
+                }
+            ))
+        );
+        assert_eq!(
+            parse_delete("delete from User where age = 27"),
+            Ok((
+                "",
+                Delete {
+                    table: TableName("User".to_string()),
+                    r#where: equals(ColumnName("age".to_string()), ScalarValue::Int(27))
+                }
+            ))
+        );
+    }
+}
@@ -1,42 +1,55 @@
-use crate::types::{Comparison, Expression, Function, ScalarValue};
+use crate::types::{Comparison, Expression, Function, Location, ScalarValue};
 
-use super::identifiers::{column_name, ws};
+use super::identifiers::{column_name, keyword, ws};
+use super::spans::{spanned, Spans};
+use super::IResult;
 
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::alphanumeric1,
-    character::complete::i32,
-    combinator::map,
-    combinator::recognize,
-    multi::many0,
-    sequence::{pair, preceded, terminated},
-    IResult,
+    bytes::complete::{escaped_transform, tag},
+    character::complete::{i32, none_of},
+    combinator::{cut, map, opt, value},
+    error::context,
+    sequence::{delimited, pair, preceded},
 };
 
 fn bool(input: &str) -> IResult<&str, bool> {
     alt((
-        map(ws(tag("true")), |_| true),
-        map(ws(tag("false")), |_| false),
+        map(keyword("true"), |_| true),
+        map(keyword("false"), |_| false),
     ))(input)
 }
 
-fn scalar_value(input: &str) -> IResult<&str, ScalarValue> {
+pub fn scalar_value(input: &str) -> IResult<&str, ScalarValue> {
     let parse_bool = map(bool, ScalarValue::Bool);
     let parse_int = map(ws(i32), ScalarValue::Int);
-    let parse_string = map(
-        ws(preceded(
-            tag("\""),
-            terminated(recognize(many0(alphanumeric1)), tag("\"")),
-        )),
-        |str: &str| ScalarValue::String(str.to_string()),
-    );
+    let parse_string = map(ws(string), ScalarValue::String);
     alt((parse_bool, alt((parse_int, parse_string))))(input)
 }
 
+// `"anything"`, where a quote or backslash inside it is escaped with a backslash
+fn string(input: &str) -> IResult<&str, String> {
+    let escaped = alt((value("\\", tag("\\")), value("\"", tag("\""))));
+
+    map(
+        delimited(
+            tag("\""),
+            opt(escaped_transform(none_of("\\\""), '\\', escaped)),
+            tag("\""),
+        ),
+        Option::unwrap_or_default,
+    )(input)
+}
+
 fn comparison(input: &str) -> IResult<&str, Comparison> {
     map(
-        pair(column_name, preceded(ws(tag("=")), scalar_value)),
+        pair(
+            column_name,
+            preceded(
+                ws(tag("=")),
+                cut(context("expected a value after `=`", scalar_value)),
+            ),
+        ),
         |(column, value)| Comparison { column, value },
     )(input)
 }
@@ -50,34 +63,49 @@ fn test_function() {
     assert_eq!(function("  &&"), Ok(("", Function::And)));
 }
 
-fn non_recursive_expression(input: &str) -> IResult<&str, Expression> {
-    let parse_bool = map(bool, Expression::Bool);
-    let parse_comparison = map(comparison, Expression::Comparison);
-    alt((parse_bool, parse_comparison))(input)
+// errors about a column in the `where` clause point at the comparison it is in
+fn spanned_comparison(input: &str) -> IResult<&str, (Expression, Spans<'_>)> {
+    map(spanned(comparison), |(comparison, text)| {
+        let spans = vec![(Location::Where(comparison.column.clone()), text)];
+        (Expression::Comparison(comparison), spans)
+    })(input)
+}
+
+fn non_recursive_expression(input: &str) -> IResult<&str, (Expression, Spans<'_>)> {
+    let parse_bool = map(bool, |bool| (Expression::Bool(bool), vec![]));
+    alt((parse_bool, spanned_comparison))(input)
 }
 
-pub fn expression(input: &str) -> IResult<&str, Expression> {
-    let parse_bool = map(bool, Expression::Bool);
-    let parse_comparison = map(comparison, Expression::Comparison);
+pub fn expression(input: &str) -> IResult<&str, (Expression, Spans<'_>)> {
     let parse_binary = map(
         pair(
             non_recursive_expression,
             pair(function, non_recursive_expression),
         ),
-        |(expr_left, (function, expr_right))| Expression::BinaryFunction {
-            function,
-            expr_left: Box::new(expr_left),
-            expr_right: Box::new(expr_right),
+        |((expr_left, mut spans), (function, (expr_right, right_spans)))| {
+            spans.extend(right_spans);
+            let expression = Expression::BinaryFunction {
+                function,
+                expr_left: Box::new(expr_left),
+                expr_right: Box::new(expr_right),
+            };
+            (expression, spans)
         },
     );
 
-    alt((parse_binary, alt((parse_bool, parse_comparison))))(input)
+    alt((parse_binary, non_recursive_expression))(input)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{comparison, expression, scalar_value};
-    use crate::{ColumnName, Comparison, Expression, Function, ScalarValue};
+    use super::{comparison, scalar_value};
+    use crate::parser::IResult;
+    use crate::{ColumnName, Comparison, Expression, Function, Location, ScalarValue};
+
+    fn expression(input: &str) -> IResult<&str, Expression> {
+        nom::combinator::map(super::expression, |(expression, _)| expression)(input)
+    }
+
     #[test]
     fn test_expression() {
         assert_eq!(expression("true"), Ok(("", Expression::Bool(true))));
@@ -120,6 +148,14 @@ mod tests {
             scalar_value("     \"dog\""),
             Ok(("", ScalarValue::String("dog".to_string())))
         );
+        assert_eq!(
+            scalar_value("\"\""),
+            Ok(("", ScalarValue::String(String::new())))
+        );
+        assert_eq!(
+            scalar_value("\"Mr \\\"Cat\\\" \\\\o/\""),
+            Ok(("", ScalarValue::String("Mr \"Cat\" \\o/".to_string())))
+        );
     }
 
     #[test]
@@ -135,4 +171,20 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn test_expression_spans() {
+        let (_, (_, spans)) = super::expression("alive = true && dog = 100").expect("expression");
+
+        assert_eq!(
+            spans,
+            vec![
+                (
+                    Location::Where(ColumnName("alive".to_string())),
+                    "alive = true"
+                ),
+                (Location::Where(ColumnName("dog".to_string())), "dog = 100"),
+            ]
+        );
+    }
 }
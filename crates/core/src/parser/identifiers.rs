@@ -1,46 +1,97 @@
+use super::IResult;
 use crate::types::{ColumnName, Constructor, TableName};
 
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_while1},
-    character::complete::{alphanumeric1, multispace0},
+    bytes::complete::{tag, tag_no_case, take_until, take_while1},
+    character::complete::{alphanumeric1, char, multispace1, not_line_ending, satisfy},
     combinator::map,
-    combinator::recognize,
+    combinator::{not, recognize, value},
     error::ParseError,
     multi::many0_count,
-    sequence::{pair, preceded},
-    IResult,
+    sequence::{delimited, pair, preceded, terminated, tuple},
 };
 
 /// A combinator that takes a parser `inner` and produces a parser that also consumes both leading and
 /// trailing whitespace, returning the output of `inner`.
 pub fn ws<'a, F, O, E: ParseError<&'a str>>(
     inner: F,
-) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
+) -> impl FnMut(&'a str) -> nom::IResult<&'a str, O, E>
 where
-    F: FnMut(&'a str) -> IResult<&'a str, O, E>,
+    F: FnMut(&'a str) -> nom::IResult<&'a str, O, E>,
 {
-    preceded(multispace0, inner)
+    preceded(blank, inner)
+}
+
+/// whitespace and comments, which are `-- to the end of the line`, `# the same`
+/// or `/* a block */`
+pub fn blank<'a, E: ParseError<&'a str>>(input: &'a str) -> nom::IResult<&'a str, (), E> {
+    let line_comment = recognize(pair(alt((tag("--"), tag("#"))), not_line_ending));
+    let block_comment = recognize(tuple((tag("/*"), take_until("*/"), tag("*/"))));
+
+    value(
+        (),
+        many0_count(alt((multispace1, line_comment, block_comment))),
+    )(input)
+}
+
+#[test]
+fn test_blank() {
+    assert_eq!(
+        blank::<()>("  -- one\n # two\n/* three\n */ select"),
+        Ok(("select", ()))
+    );
+    assert_eq!(blank::<()>("/* unfinished"), Ok(("/* unfinished", ())));
+}
+
+/// a keyword, in any case, which can't run on into the word after it, so
+/// `selectname` is not `select name`
+pub fn keyword<'a>(word: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    ws(terminated(
+        tag_no_case(word),
+        not(satisfy(|c: char| c.is_alphanumeric() || c == '_')),
+    ))
+}
+
+#[test]
+fn test_keyword() {
+    assert_eq!(keyword("select")(" SeLeCt name"), Ok((" name", "SeLeCt")));
+    assert_eq!(keyword("select")("select\nname"), Ok(("\nname", "select")));
+    assert!(keyword("select")("selectname").is_err());
 }
 
 // parse at least one uppercase char
-fn uppercase_char<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, &'a str, E> {
+fn uppercase_char<'a, E: ParseError<&'a str>>(i: &'a str) -> nom::IResult<&'a str, &'a str, E> {
     let chars = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
     take_while1(move |c| chars.contains(c))(i)
 }
 
 // parse at least one lowercase char
-fn lowercase_char<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, &'a str, E> {
+fn lowercase_char<'a, E: ParseError<&'a str>>(i: &'a str) -> nom::IResult<&'a str, &'a str, E> {
     let chars = "abcdefghijklmnopqrstuvwxyz";
     take_while1(move |c| chars.contains(c))(i)
 }
 
+// a name starting with `first`, then letters, numbers and underscores
+fn unquoted<'a, F>(first: F) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str>
+where
+    F: FnMut(&'a str) -> IResult<&'a str, &'a str>,
+{
+    recognize(pair(first, many0_count(alt((alphanumeric1, tag("_"))))))
+}
+
+// a name in backticks or double quotes can be anything but the quote itself,
+// so it can be a keyword or start with either case
+fn quoted(input: &str) -> IResult<&str, &str> {
+    alt((
+        delimited(char('`'), take_while1(|c| c != '`'), char('`')),
+        delimited(char('"'), take_while1(|c| c != '"'), char('"')),
+    ))(input)
+}
+
 pub fn table_name(input: &str) -> IResult<&str, TableName> {
     map(
-        ws(recognize(pair(
-            uppercase_char,
-            many0_count(alt((alphanumeric1, tag("_")))),
-        ))),
+        ws(alt((quoted, unquoted(uppercase_char)))),
         |ident: &str| TableName(ident.to_string()),
     )(input)
 }
@@ -63,10 +114,7 @@ fn test_table_name() {
 
 pub fn constructor(input: &str) -> IResult<&str, Constructor> {
     map(
-        ws(recognize(pair(
-            uppercase_char,
-            many0_count(alt((alphanumeric1, tag("_")))),
-        ))),
+        ws(alt((quoted, unquoted(uppercase_char)))),
         |ident: &str| Constructor(ident.to_string()),
     )(input)
 }
@@ -88,10 +136,7 @@ fn test_constructor() {
 
 pub fn column_name(input: &str) -> IResult<&str, ColumnName> {
     map(
-        ws(recognize(pair(
-            lowercase_char,
-            many0_count(alt((alphanumeric1, tag("_")))),
-        ))),
+        ws(alt((quoted, unquoted(lowercase_char)))),
         |ident: &str| ColumnName(ident.to_string()),
     )(input)
 }
@@ -109,4 +154,14 @@ fn test_column_name() {
         column_name("horse"),
         Ok(("", ColumnName("horse".to_string())))
     );
+
+    assert_eq!(
+        column_name("`from`"),
+        Ok(("", ColumnName("from".to_string())))
+    );
+
+    assert_eq!(
+        column_name("\"Last Name\""),
+        Ok(("", ColumnName("Last Name".to_string())))
+    );
 }
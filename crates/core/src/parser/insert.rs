@@ -0,0 +1,257 @@
+use super::expression::scalar_value;
+use super::identifiers::{column_name, constructor, keyword, table_name, ws};
+use super::spans::{spanned, Spans};
+use super::IResult;
+use crate::types::{ColumnName, Insert, InsertValue, Location, OnConflict, ScalarValue};
+use std::collections::BTreeMap;
+
+use nom::{
+    bytes::complete::tag,
+    character::complete::i32,
+    combinator::{cut, map, not, opt},
+    error::context,
+    sequence::{delimited, pair, preceded, terminated, tuple},
+};
+
+// `insert into User key 1 { name: "Egg", age: 27 }`
+// the key is left out for `autoincrement` tables
+pub fn parse_insert(input: &str) -> IResult<&str, Insert> {
+    map(insert_with_spans, |(insert, _)| insert)(input)
+}
+
+pub fn insert_with_spans(input: &str) -> IResult<&str, (Insert, Spans<'_>)> {
+    map(
+        tuple((
+            preceded(
+                pair(
+                    keyword("insert"),
+                    cut(context("expected `into` after `insert`", keyword("into"))),
+                ),
+                cut(context(
+                    "expected a table name after `into`",
+                    spanned(table_name),
+                )),
+            ),
+            // `key {` is a constructor called `Key`, not a key clause
+            opt(preceded(
+                terminated(keyword("key"), not(ws(tag("{")))),
+                cut(context("expected a number after `key`", spanned(ws(i32)))),
+            )),
+            cut(context(
+                "expected values like `{ name: \"Egg\" }`",
+                insert_value,
+            )),
+            on_conflict,
+            returning_key,
+        )),
+        |((table, table_text), key, (value, value_spans), on_conflict, returning_key)| {
+            let mut spans = vec![(Location::Table, table_text)];
+            if let Some((_, key_text)) = key {
+                spans.push((Location::Key, key_text));
+            }
+            spans.extend(value_spans);
+            let insert = Insert {
+                table,
+                key: key.map(|(key, _)| key),
+                value,
+                on_conflict,
+                returning_key,
+            };
+            (insert, spans)
+        },
+    )(input)
+}
+
+// `{ name: "Egg" }` or `Cat { name: "Mr Cat" }`
+fn insert_value(input: &str) -> IResult<&str, (InsertValue, Spans<'_>)> {
+    map(
+        pair(opt(spanned(constructor)), spanned(values)),
+        |(maybe_constructor, ((values, column_spans), values_text))| {
+            let mut spans = vec![];
+            let value = match maybe_constructor {
+                Some((constructor, constructor_text)) => {
+                    spans.push((Location::Constructor, constructor_text));
+                    InsertValue::Multiple {
+                        constructor,
+                        values,
+                    }
+                }
+                None => InsertValue::Single { values },
+            };
+            spans.push((Location::Values, values_text));
+            spans.extend(column_spans);
+            (value, spans)
+        },
+    )(input)
+}
+
+fn values(input: &str) -> IResult<&str, (BTreeMap<ColumnName, ScalarValue>, Spans<'_>)> {
+    let parse_pair = pair(
+        column_name,
+        preceded(
+            cut(context("expected `:` after column name", ws(tag(":")))),
+            cut(context("expected a value after `:`", scalar_value)),
+        ),
+    );
+
+    map(
+        delimited(
+            ws(tag("{")),
+            nom::multi::separated_list1(ws(tag(",")), spanned(parse_pair)),
+            cut(context("expected `,` or `}` after value", ws(tag("}")))),
+        ),
+        |pairs| {
+            let mut values = BTreeMap::new();
+            let mut spans = vec![];
+            for ((column, value), text) in pairs {
+                spans.push((Location::Column(column.clone()), text));
+                values.insert(column, value);
+            }
+            (values, spans)
+        },
+    )(input)
+}
+
+// `on conflict replace`, defaulting to failing on duplicate keys
+fn on_conflict(input: &str) -> IResult<&str, OnConflict> {
+    map(
+        opt(preceded(
+            pair(keyword("on"), keyword("conflict")),
+            keyword("replace"),
+        )),
+        |maybe_replace| match maybe_replace {
+            Some(_) => OnConflict::Replace,
+            None => OnConflict::Fail,
+        },
+    )(input)
+}
+
+// `returning key`
+fn returning_key(input: &str) -> IResult<&str, bool> {
+    map(
+        opt(pair(keyword("returning"), keyword("key"))),
+        |maybe_returning| maybe_returning.is_some(),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_insert;
+    use crate::{ColumnName, Constructor, Insert, InsertValue, OnConflict, ScalarValue, TableName};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_single_constructor_insert() {
+        let mut values = BTreeMap::new();
+        values.insert(ColumnName("age".to_string()), ScalarValue::Int(27));
+        values.insert(
+            ColumnName("name".to_string()),
+            ScalarValue::String("Egg".to_string()),
+        );
+
+        assert_eq!(
+            parse_insert("insert into User key 1 { age: 27, name: \"Egg\" }"),
+            Ok((
+                "",
+                Insert {
+                    table: TableName("User".to_string()),
+                    key: Some(1),
+                    value: InsertValue::Single { values },
+                    on_conflict: OnConflict::Fail,
+                    returning_key: false,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_multiple_constructor_insert() {
+        let mut values = BTreeMap::new();
+        values.insert(
+            ColumnName("likes_stick".to_string()),
+            ScalarValue::Bool(true),
+        );
+
+        assert_eq!(
+            parse_insert("insert into Pet key 2 Dog { likes_stick: true }"),
+            Ok((
+                "",
+                Insert {
+                    table: TableName("Pet".to_string()),
+                    key: Some(2),
+                    value: InsertValue::Multiple {
+                        constructor: Constructor("Dog".to_string()),
+                        values
+                    },
+                    on_conflict: OnConflict::Fail,
+                    returning_key: false,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_insert_on_conflict_replace() {
+        let mut values = BTreeMap::new();
+        values.insert(ColumnName("age".to_string()), ScalarValue::Int(28));
+
+        assert_eq!(
+            parse_insert("insert into User key 1 { age: 28 } on conflict replace"),
+            Ok((
+                "",
+                Insert {
+                    table: TableName("User".to_string()),
+                    key: Some(1),
+                    value: InsertValue::Single { values },
+                    on_conflict: OnConflict::Replace,
+                    returning_key: false,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_insert_returning_key() {
+        let mut values = BTreeMap::new();
+        values.insert(ColumnName("age".to_string()), ScalarValue::Int(28));
+
+        assert_eq!(
+            parse_insert("insert into User { age: 28 } returning key"),
+            Ok((
+                "",
+                Insert {
+                    table: TableName("User".to_string()),
+                    key: None,
+                    value: InsertValue::Single { values },
+                    on_conflict: OnConflict::Fail,
+                    returning_key: true,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_insert_key_constructor() {
+        let mut values = BTreeMap::new();
+        values.insert(ColumnName("a".to_string()), ScalarValue::Int(1));
+        let insert = |key| Insert {
+            table: TableName("Lock".to_string()),
+            key,
+            value: InsertValue::Multiple {
+                constructor: Constructor("Key".to_string()),
+                values: values.clone(),
+            },
+            on_conflict: OnConflict::Fail,
+            returning_key: false,
+        };
+
+        assert_eq!(
+            parse_insert("insert into Lock Key { a: 1 }"),
+            Ok(("", insert(None)))
+        );
+        assert_eq!(
+            parse_insert("insert into Lock key 3 Key { a: 1 }"),
+            Ok(("", insert(Some(3))))
+        );
+    }
+}
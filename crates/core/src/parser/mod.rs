@@ -1,7 +1,25 @@
+mod alter;
+mod catalog;
+mod delete;
 mod expression;
 mod identifiers;
+mod insert;
 mod select;
+mod spans;
+mod statement;
 mod table;
+mod transaction;
+mod update;
 
+pub use alter::parse_alter;
+pub use catalog::parse_catalog;
+pub use delete::parse_delete;
+pub use insert::parse_insert;
 pub use select::parse_select;
+pub use statement::{parse_script, parse_statement, script_is_complete};
 pub use table::parse_table;
+pub use transaction::parse_transaction;
+pub use update::parse_update;
+
+/// parsers keep the `context` they failed in, so errors can say what was expected
+pub type IResult<I, O> = nom::IResult<I, O, nom::error::VerboseError<I>>;
@@ -1,70 +1,120 @@
 use super::expression::expression;
-use super::identifiers::{column_name, constructor, table_name, ws};
+use super::identifiers::{column_name, constructor, keyword, table_name, ws};
+use super::spans::{spanned, Spans};
+use super::IResult;
 use crate::empty_where;
-use crate::types::{Expression, Select, SelectColumns};
+use crate::types::{ColumnName, Expression, Location, Select, SelectColumns};
 
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    combinator::{map, opt},
-    sequence::{delimited, pair, preceded},
-    IResult,
+    combinator::{cut, map, opt},
+    error::context,
+    sequence::{delimited, pair, preceded, tuple},
 };
 
-fn select_columns(input: &str) -> IResult<&str, SelectColumns> {
-    alt((select_just_columns, select_constructor))(input)
+fn select_columns(input: &str) -> IResult<&str, (SelectColumns, Spans<'_>)> {
+    // a constructor is only a constructor if columns follow it in braces
+    alt((select_constructor, select_just_columns))(input)
 }
 
 // `name,age,title`
-fn select_just_columns(input: &str) -> IResult<&str, SelectColumns> {
-    map(
-        nom::multi::separated_list1(ws(tag(",")), column_name),
-        |columns| SelectColumns::SelectColumns { columns },
-    )(input)
+fn select_just_columns(input: &str) -> IResult<&str, (SelectColumns, Spans<'_>)> {
+    map(column_list, |(columns, spans)| {
+        (SelectColumns::SelectColumns { columns }, spans)
+    })(input)
 }
 
 // `RGB{red,green,blue}`
-fn select_constructor(input: &str) -> IResult<&str, SelectColumns> {
+fn select_constructor(input: &str) -> IResult<&str, (SelectColumns, Spans<'_>)> {
     map(
         pair(
-            constructor,
+            spanned(constructor),
             delimited(
                 ws(tag("{")),
-                nom::multi::separated_list1(ws(tag(",")), column_name),
-                ws(tag("}")),
+                column_list,
+                cut(context(
+                    "expected `}` after constructor columns",
+                    ws(tag("}")),
+                )),
             ),
         ),
-        |(constructor, columns)| SelectColumns::SelectConstructor {
-            constructor,
-            columns,
+        |((constructor, constructor_text), (columns, mut spans))| {
+            spans.insert(0, (Location::Constructor, constructor_text));
+            (
+                SelectColumns::SelectConstructor {
+                    constructor,
+                    columns,
+                },
+                spans,
+            )
         },
     )(input)
 }
 
-pub fn parse_select(input: &str) -> IResult<&str, Select> {
+fn column_list(input: &str) -> IResult<&str, (Vec<ColumnName>, Spans<'_>)> {
     map(
-        pair(
-            preceded(ws(tag("select ")), select_columns),
-            pair(preceded(ws(tag("from")), table_name), r#where),
-        ),
-        |(select_columns, (table_name, expression))| Select {
-            table: table_name,
-            columns: select_columns,
-            r#where: expression,
+        nom::multi::separated_list1(ws(tag(",")), spanned(column_name)),
+        |columns| {
+            columns
+                .into_iter()
+                .map(|(column, text)| (column.clone(), (Location::Column(column), text)))
+                .unzip()
         },
     )(input)
 }
 
-fn r#where(input: &str) -> IResult<&str, Expression> {
+pub fn parse_select(input: &str) -> IResult<&str, Select> {
+    map(select_with_spans, |(select, _)| select)(input)
+}
+
+pub fn select_with_spans(input: &str) -> IResult<&str, (Select, Spans<'_>)> {
     map(
-        opt(preceded(ws(tag("where")), expression)),
-        |maybe_exp| match maybe_exp {
-            Some(expression) => expression,
-            None => empty_where(),
+        tuple((
+            preceded(
+                keyword("select"),
+                cut(context(
+                    "expected a column list after `select`",
+                    select_columns,
+                )),
+            ),
+            preceded(
+                cut(context(
+                    "expected `from` after column list",
+                    keyword("from"),
+                )),
+                cut(context(
+                    "expected a table name after `from`",
+                    spanned(table_name),
+                )),
+            ),
+            r#where,
+        )),
+        |((columns, mut spans), (table, table_text), (r#where, where_spans))| {
+            spans.push((Location::Table, table_text));
+            spans.extend(where_spans);
+            (
+                Select {
+                    table,
+                    columns,
+                    r#where,
+                },
+                spans,
+            )
         },
     )(input)
 }
 
+pub fn r#where(input: &str) -> IResult<&str, (Expression, Spans<'_>)> {
+    map(
+        opt(preceded(
+            keyword("where"),
+            cut(context("expected an expression after `where`", expression)),
+        )),
+        |maybe_exp| maybe_exp.unwrap_or_else(|| (empty_where(), vec![])),
+    )(input)
+}
+
 #[cfg(test)]
 mod tests {
     use super::{parse_select, select_columns};
@@ -108,7 +158,8 @@ mod tests {
     #[test]
     fn test_select_columns() {
         assert_eq!(
-            select_columns("RGB{ red ,  green ,  blue }"),
+            select_columns("RGB{ red ,  green ,  blue }")
+                .map(|(rest, (columns, _))| (rest, columns)),
             Ok((
                 "",
                 SelectColumns::SelectConstructor {
@@ -123,7 +174,7 @@ mod tests {
         );
 
         assert_eq!(
-            select_columns("horse  ,    course,eggs"),
+            select_columns("horse  ,    course,eggs").map(|(rest, (columns, _))| (rest, columns)),
             Ok((
                 "",
                 SelectColumns::SelectColumns {
@@ -0,0 +1,79 @@
+use super::identifiers::blank;
+use super::IResult;
+use crate::types::{Location, SourceMap, Span};
+
+use nom::{error::VerboseError, Offset};
+
+/// the text each part of a statement was parsed from
+pub type Spans<'a> = Vec<(Location, &'a str)>;
+
+/// runs `inner`, also returning the text it matched without any leading whitespace or comments
+pub fn spanned<'a, O, F>(mut inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, (O, &'a str)>
+where
+    F: FnMut(&'a str) -> IResult<&'a str, O>,
+{
+    move |input| {
+        let (start, ()) = blank::<VerboseError<_>>(input)?;
+        let (rest, output) = inner(start)?;
+        Ok((rest, (output, &start[..start.offset(rest)])))
+    }
+}
+
+/// turn the text of each part of a statement into where it is in `source`,
+/// which the text must have been parsed from
+pub fn source_map(source: &str, spans: Spans<'_>) -> SourceMap {
+    SourceMap(
+        spans
+            .into_iter()
+            .map(|(location, text)| {
+                let start = source.offset(text);
+                let span = Span {
+                    start,
+                    end: start + text.len(),
+                };
+                (location, span)
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_script, ColumnName, Location, Span};
+
+    #[test]
+    fn test_source_map() {
+        let source = "select id, name from User where id = 1 && name = \"Egg\"";
+        let (_, _, map) = parse_script(source).expect("parse_script").remove(0);
+
+        assert_eq!(
+            map.span(&Location::Table),
+            Some(Span { start: 21, end: 25 })
+        );
+        assert_eq!(
+            map.span(&Location::Column(ColumnName("name".to_string()))),
+            Some(Span { start: 11, end: 15 })
+        );
+        assert_eq!(
+            map.span(&Location::Where(ColumnName("name".to_string()))),
+            Some(Span { start: 42, end: 54 })
+        );
+        assert_eq!(&source[42..54], "name = \"Egg\"");
+    }
+
+    #[test]
+    fn test_insert_source_map() {
+        let source = "show tables;\ninsert into User key 1 Cat { age: 27 }";
+        let (_, _, map) = parse_script(source).expect("parse_script").remove(1);
+
+        assert_eq!(map.span(&Location::Key), Some(Span { start: 34, end: 35 }));
+        assert_eq!(
+            map.span(&Location::Constructor),
+            Some(Span { start: 36, end: 39 })
+        );
+        assert_eq!(
+            map.span(&Location::Column(ColumnName("age".to_string()))),
+            Some(Span { start: 42, end: 49 })
+        );
+    }
+}
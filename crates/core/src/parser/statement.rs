@@ -0,0 +1,251 @@
+use super::alter::alter_with_spans;
+use super::catalog::parse_catalog;
+use super::delete::delete_with_spans;
+use super::identifiers::{blank, ws};
+use super::insert::insert_with_spans;
+use super::select::select_with_spans;
+use super::spans::{source_map, Spans};
+use super::table::table_with_spans;
+use super::transaction::parse_transaction;
+use super::update::update_with_spans;
+use super::IResult;
+use crate::diagnostic::{parse_error, token_span};
+use crate::types::{ParseError, SourceMap, Span, Statement};
+
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{map, opt},
+    error::context,
+    sequence::terminated,
+    Offset,
+};
+
+/// parse one whole statement, with an optional `;` on the end.
+/// anything left over afterwards is an error
+pub fn parse_statement(source: &str) -> Result<Statement, ParseError> {
+    let (rest, (statement, _)) = terminated(statement, opt(ws(tag(";"))))(source)
+        .map_err(|error| parse_error(source, &error))?;
+
+    let rest = skip_blank(rest);
+    if rest.is_empty() {
+        Ok(statement)
+    } else {
+        Err(ParseError {
+            span: token_span(source, rest),
+            message: "unexpected input after the end of the statement".to_string(),
+        })
+    }
+}
+
+/// parse every statement in a script, along with where each one and each of its
+/// parts are in `source`. statements are separated by `;`, which is optional
+/// after the last one
+pub fn parse_script(source: &str) -> Result<Vec<(Span, Statement, SourceMap)>, ParseError> {
+    let mut statements = vec![];
+    let mut rest = skip_blank(source);
+
+    while !rest.is_empty() {
+        let (after, (statement, spans)) =
+            statement(rest).map_err(|error| parse_error(source, &error))?;
+        let start = source.offset(rest);
+        let span = Span {
+            start,
+            end: start + rest.offset(after),
+        };
+        statements.push((span, statement, source_map(source, spans)));
+
+        let (after, semicolon) = opt(ws(tag::<_, _, ()>(";")))(after).unwrap_or((after, None));
+        rest = skip_blank(after);
+        if semicolon.is_none() && !rest.is_empty() {
+            return Err(ParseError {
+                span: token_span(source, rest),
+                message: "expected `;` after the end of the statement".to_string(),
+            });
+        }
+    }
+
+    Ok(statements)
+}
+
+/// whether every statement in `source` has been ended with a `;`, which is
+/// also true when there are none. a `;` in a comment, string or quoted name
+/// doesn't count
+pub fn script_is_complete(source: &str) -> bool {
+    let mut rest = skip_blank(source);
+    let mut last = None;
+    while let Some(next) = rest.chars().next() {
+        let length = match next {
+            '"' | '`' => quoted_length(rest, next),
+            _ => next.len_utf8(),
+        };
+        last = Some(next);
+        rest = skip_blank(&rest[length..]);
+    }
+    last.is_none() || last == Some(';')
+}
+
+// the length of the string or quoted name at the start of `input`, which is
+// all of it if it is never closed
+fn quoted_length(input: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (index, next) in input.char_indices().skip(1) {
+        match next {
+            _ if escaped => escaped = false,
+            '\\' if quote == '"' => escaped = true,
+            _ if next == quote => return index + 1,
+            _ => {}
+        }
+    }
+    input.len()
+}
+
+fn skip_blank(input: &str) -> &str {
+    blank::<()>(input).map_or(input, |(rest, ())| rest)
+}
+
+// the statement, and the text each part of it came from
+fn statement(input: &str) -> IResult<&str, (Statement, Spans<'_>)> {
+    fn with<'a, T>(
+        statement: fn(T) -> Statement,
+    ) -> impl Fn((T, Spans<'a>)) -> (Statement, Spans<'a>) {
+        move |(parsed, spans)| (statement(parsed), spans)
+    }
+
+    context(
+        "expected a statement such as `select`, `insert`, `update`, `delete`, `type` or `alter`",
+        alt((
+            map(table_with_spans, with(Statement::Table)),
+            map(select_with_spans, with(Statement::Select)),
+            map(insert_with_spans, with(Statement::Insert)),
+            map(update_with_spans, with(Statement::Update)),
+            map(delete_with_spans, with(Statement::Delete)),
+            map(alter_with_spans, with(Statement::Alter)),
+            map(parse_catalog, |catalog| {
+                (Statement::Catalog(catalog), vec![])
+            }),
+            map(parse_transaction, |command| {
+                (Statement::Transaction(command), vec![])
+            }),
+        )),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_script, parse_statement, script_is_complete};
+    use crate::{
+        empty_where, CatalogCommand, ColumnName, ParseError, Select, SelectColumns, Span,
+        Statement, TableName,
+    };
+
+    #[test]
+    fn test_statement() {
+        let select = Statement::Select(Select {
+            table: TableName("User".to_string()),
+            columns: SelectColumns::SelectColumns {
+                columns: vec![ColumnName("name".to_string())],
+            },
+            r#where: empty_where(),
+        });
+
+        assert_eq!(parse_statement("select name from User"), Ok(select));
+        assert_eq!(
+            parse_statement("  show tables ;  \n"),
+            Ok(Statement::Catalog(CatalogCommand::ShowTables))
+        );
+    }
+
+    #[test]
+    fn test_keywords_and_quoted_names() {
+        let select = Statement::Select(Select {
+            table: TableName("User".to_string()),
+            columns: SelectColumns::SelectColumns {
+                columns: vec![
+                    ColumnName("from".to_string()),
+                    ColumnName("Last Name".to_string()),
+                ],
+            },
+            r#where: empty_where(),
+        });
+
+        assert_eq!(
+            parse_statement("SELECT\n`from`,\"Last Name\"\nFrom\tUser Where TRUE"),
+            Ok(select)
+        );
+    }
+
+    #[test]
+    fn test_trailing_input() {
+        assert_eq!(
+            parse_statement("select name from User blah"),
+            Err(ParseError {
+                span: Span { start: 22, end: 26 },
+                message: "unexpected input after the end of the statement".to_string()
+            })
+        );
+        assert_eq!(
+            parse_statement("select name from User;;"),
+            Err(ParseError {
+                span: Span { start: 22, end: 23 },
+                message: "unexpected input after the end of the statement".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_script() {
+        let script = "
+            -- the users
+            type User { name: String };
+            insert into User key 1 { name: \"Egg\" }; # the first one
+            /* and everyone
+               else */
+            select name from User
+        ";
+        let statements = parse_script(script).expect("parse_script");
+
+        assert_eq!(statements.len(), 3);
+        let (span, statement, _) = &statements[2];
+        assert_eq!(&script[span.start..span.end], "select name from User");
+        assert!(matches!(statement, Statement::Select(_)));
+
+        assert_eq!(parse_script(" -- nothing to see here "), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_script_without_semicolon() {
+        assert_eq!(
+            parse_script("show tables\nshow tables"),
+            Err(ParseError {
+                span: Span { start: 12, end: 16 },
+                message: "expected `;` after the end of the statement".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_script_is_complete() {
+        assert!(script_is_complete(""));
+        assert!(script_is_complete(" -- nothing yet"));
+        assert!(script_is_complete("show tables; -- a note"));
+        assert!(script_is_complete("show tables; /* a; */ show tables;\n"));
+        assert!(!script_is_complete("show tables; show tables"));
+        assert!(!script_is_complete("show tables -- not yet;"));
+        assert!(!script_is_complete("insert into User key 1 { name: \"a;"));
+        assert!(script_is_complete(
+            "insert into User key 1 { name: \"a \\\" ;\" };"
+        ));
+    }
+
+    #[test]
+    fn test_unknown_statement() {
+        assert_eq!(
+            parse_statement("explode User"),
+            Err(ParseError {
+                span: Span { start: 0, end: 7 },
+                message: "expected a statement such as `select`, `insert`, `update`, `delete`, `type` or `alter`".to_string()
+            })
+        );
+    }
+}
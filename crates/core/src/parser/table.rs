@@ -1,67 +1,152 @@
-use super::identifiers::{column_name, constructor, table_name, ws};
-use crate::types::{ColumnName, Columns, ScalarType, Table};
+use super::expression::scalar_value;
+use super::identifiers::{column_name, constructor, keyword, table_name, ws};
+use super::spans::{spanned, Spans};
+use super::IResult;
+use crate::types::{ColumnName, Columns, Defaults, Location, ScalarType, ScalarValue, Table};
 use std::collections::BTreeMap;
 
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    combinator::map,
-    sequence::{delimited, pair, preceded},
-    IResult,
+    combinator::{cut, map, opt},
+    error::context,
+    sequence::{delimited, pair, preceded, tuple},
 };
 
 pub fn parse_table(input: &str) -> IResult<&str, Table> {
+    map(table_with_spans, |(table, _)| table)(input)
+}
+
+pub fn table_with_spans(input: &str) -> IResult<&str, (Table, Spans<'_>)> {
     map(
-        pair(preceded(ws(tag("type")), table_name), columns),
-        |(table_name, columns)| Table {
-            name: table_name,
-            columns,
+        tuple((
+            preceded(
+                keyword("type"),
+                cut(context(
+                    "expected a table name after `type`",
+                    spanned(table_name),
+                )),
+            ),
+            auto_increment,
+            cut(context("expected columns like `{ name: String }`", columns)),
+        )),
+        |((name, name_text), auto_increment, (columns, defaults, mut spans))| {
+            spans.insert(0, (Location::Table, name_text));
+            let table = Table {
+                name,
+                columns,
+                auto_increment,
+                defaults,
+            };
+            (table, spans)
         },
     )(input)
 }
 
-fn columns(input: &str) -> IResult<&str, Columns> {
-    let single = map(single_constructor, Columns::SingleConstructor);
+// `type User autoincrement { ... }`
+fn auto_increment(input: &str) -> IResult<&str, bool> {
+    map(opt(keyword("autoincrement")), |maybe_auto| {
+        maybe_auto.is_some()
+    })(input)
+}
+
+type DeclaredColumns<'a> = (Columns, Defaults, Spans<'a>);
+
+fn columns(input: &str) -> IResult<&str, DeclaredColumns<'_>> {
+    let single = map(single_constructor, |(columns, defaults, spans)| {
+        let defaults = Defaults {
+            columns: defaults,
+            ..Defaults::default()
+        };
+        (Columns::SingleConstructor(columns), defaults, spans)
+    });
     let multiple = map(
         delimited(
             ws(tag("{")),
-            nom::multi::separated_list1(ws(tag(",")), pair(constructor, single_constructor)),
-            ws(tag("}")),
+            nom::multi::separated_list1(
+                ws(tag(",")),
+                pair(spanned(constructor), single_constructor),
+            ),
+            cut(context(
+                "expected `,` or `}` after constructor",
+                ws(tag("}")),
+            )),
         ),
         |constructors| {
             let mut all_constructors = BTreeMap::new();
-            for (constructor, columns) in constructors {
+            let mut all_defaults = Defaults::default();
+            let mut all_spans = vec![];
+            for ((constructor, constructor_text), (columns, defaults, spans)) in constructors {
+                if !defaults.is_empty() {
+                    all_defaults
+                        .constructors
+                        .insert(constructor.clone(), defaults);
+                }
                 all_constructors.insert(constructor, columns);
+                all_spans.push((Location::Constructor, constructor_text));
+                all_spans.extend(spans);
             }
-            Columns::MultipleConstructors(all_constructors)
+            (
+                Columns::MultipleConstructors(all_constructors),
+                all_defaults,
+                all_spans,
+            )
         },
     );
-    alt((single, multiple))(input)
+    // a constructor is only a constructor if columns follow it in braces
+    alt((multiple, single))(input)
 }
 
-fn scalar_type(input: &str) -> IResult<&str, ScalarType> {
+pub fn scalar_type(input: &str) -> IResult<&str, ScalarType> {
     let bool = map(ws(tag("Bool")), |_| ScalarType::Bool);
     let int = map(ws(tag("Int")), |_| ScalarType::Int);
     let string = map(ws(tag("String")), |_| ScalarType::String);
 
-    alt((bool, alt((int, string))))(input)
+    context(
+        "expected `Bool`, `Int` or `String`",
+        alt((bool, alt((int, string)))),
+    )(input)
 }
 
-fn single_constructor(input: &str) -> IResult<&str, BTreeMap<ColumnName, ScalarType>> {
-    let parse_pair = pair(column_name, preceded(ws(tag(":")), scalar_type));
+/// the columns of a constructor, defaults for any of them, and where each was declared
+type ColumnsWithDefaults<'a> = (
+    BTreeMap<ColumnName, ScalarType>,
+    BTreeMap<ColumnName, ScalarValue>,
+    Spans<'a>,
+);
+
+// `{ age: Int = 0, name: String }`
+pub fn single_constructor(input: &str) -> IResult<&str, ColumnsWithDefaults<'_>> {
+    let parse_column = tuple((
+        column_name,
+        preceded(
+            cut(context("expected `:` after column name", ws(tag(":")))),
+            cut(scalar_type),
+        ),
+        opt(preceded(
+            ws(tag("=")),
+            cut(context("expected a default value after `=`", scalar_value)),
+        )),
+    ));
 
     map(
         delimited(
             ws(tag("{")),
-            nom::multi::separated_list1(ws(tag(",")), parse_pair),
-            ws(tag("}")),
+            nom::multi::separated_list1(ws(tag(",")), spanned(parse_column)),
+            cut(context("expected `,` or `}` after column", ws(tag("}")))),
         ),
-        |pairs| {
-            let mut btree = BTreeMap::new();
-            for (key, value) in pairs {
-                btree.insert(key, value);
+        |parsed_columns| {
+            let mut columns = BTreeMap::new();
+            let mut defaults = BTreeMap::new();
+            let mut spans = vec![];
+            for ((column_name, scalar_type, default), text) in parsed_columns {
+                if let Some(default) = default {
+                    defaults.insert(column_name.clone(), default);
+                }
+                spans.push((Location::Column(column_name.clone()), text));
+                columns.insert(column_name, scalar_type);
             }
-            btree
+            (columns, defaults, spans)
         },
     )(input)
 }
@@ -69,7 +154,9 @@ fn single_constructor(input: &str) -> IResult<&str, BTreeMap<ColumnName, ScalarT
 #[cfg(test)]
 mod tests {
     use super::parse_table;
-    use crate::{ColumnName, Columns, Constructor, ScalarType, Table, TableName};
+    use crate::{
+        ColumnName, Columns, Constructor, Defaults, ScalarType, ScalarValue, Table, TableName,
+    };
     use std::collections::BTreeMap;
 
     #[test]
@@ -85,7 +172,9 @@ mod tests {
                 "",
                 Table {
                     name: TableName("User".to_string()),
-                    columns: Columns::SingleConstructor(columns)
+                    columns: Columns::SingleConstructor(columns),
+                    auto_increment: false,
+                    defaults: Defaults::default(),
                 }
             ))
         );
@@ -113,9 +202,102 @@ mod tests {
                 "",
                 Table {
                     name: TableName("Color".to_string()),
-                    columns: Columns::MultipleConstructors(constructors)
+                    columns: Columns::MultipleConstructors(constructors),
+                    auto_increment: false,
+                    defaults: Defaults::default(),
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_auto_increment_table() {
+        let mut columns = BTreeMap::new();
+        columns.insert(ColumnName("name".to_string()), ScalarType::String);
+
+        assert_eq!(
+            parse_table("type User autoincrement { name: String }"),
+            Ok((
+                "",
+                Table {
+                    name: TableName("User".to_string()),
+                    columns: Columns::SingleConstructor(columns),
+                    auto_increment: true,
+                    defaults: Defaults::default(),
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for table_sql in [
+            "type User autoincrement { age: Int, name: String }",
+            "type Color { Greyscale { value: Int }, RGB { blue: Int, green: Int, red: Int } }",
+            "type User { age: Int = 0, name: String = \"Egg\", nice: Bool = true }",
+            "type `user` { `Last Name`: String, `from`: Int }",
+        ] {
+            let (_, table) = parse_table(table_sql).expect("parse_table");
+            assert_eq!(table.to_string(), table_sql);
+            assert_eq!(parse_table(&table.to_string()), Ok(("", table)));
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        let mut columns = BTreeMap::new();
+        columns.insert(ColumnName("age".to_string()), ScalarType::Int);
+        columns.insert(ColumnName("name".to_string()), ScalarType::String);
+
+        let mut defaults = Defaults::default();
+        defaults.insert(None, ColumnName("age".to_string()), ScalarValue::Int(0));
+
+        assert_eq!(
+            parse_table("type User { age: Int = 0, name: String }"),
+            Ok((
+                "",
+                Table {
+                    name: TableName("User".to_string()),
+                    columns: Columns::SingleConstructor(columns),
+                    auto_increment: false,
+                    defaults,
                 }
             ))
         );
     }
+
+    #[test]
+    fn test_defaults_belong_to_their_constructor() {
+        let (_, table) = parse_table(
+            "type Pet { Cat { age: Int = 0 }, Dog { age: Int }, Fish { age: Int = 1 } }",
+        )
+        .expect("parse_table");
+
+        let cat = Constructor("Cat".to_string());
+        let dog = Constructor("Dog".to_string());
+        let fish = Constructor("Fish".to_string());
+        let age = ColumnName("age".to_string());
+
+        assert_eq!(
+            table
+                .defaults
+                .get(Some(&cat))
+                .and_then(|defaults| defaults.get(&age)),
+            Some(&ScalarValue::Int(0))
+        );
+        assert_eq!(table.defaults.get(Some(&dog)), None);
+        assert_eq!(
+            table
+                .defaults
+                .get(Some(&fish))
+                .and_then(|defaults| defaults.get(&age)),
+            Some(&ScalarValue::Int(1))
+        );
+
+        // and each is printed back under the constructor that declared it
+        assert_eq!(
+            table.to_string(),
+            "type Pet { Cat { age: Int = 0 }, Dog { age: Int }, Fish { age: Int = 1 } }"
+        );
+    }
 }
@@ -0,0 +1,37 @@
+use super::identifiers::keyword;
+use super::IResult;
+use crate::types::TransactionCommand;
+
+use nom::{branch::alt, combinator::map};
+
+// `begin`, `commit` or `rollback`
+pub fn parse_transaction(input: &str) -> IResult<&str, TransactionCommand> {
+    alt((
+        map(keyword("begin"), |_| TransactionCommand::Begin),
+        map(keyword("commit"), |_| TransactionCommand::Commit),
+        map(keyword("rollback"), |_| TransactionCommand::Rollback),
+    ))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_transaction;
+    use crate::TransactionCommand;
+
+    #[test]
+    fn test_transaction() {
+        assert_eq!(
+            parse_transaction("begin"),
+            Ok(("", TransactionCommand::Begin))
+        );
+        assert_eq!(
+            parse_transaction("COMMIT;"),
+            Ok((";", TransactionCommand::Commit))
+        );
+        assert_eq!(
+            parse_transaction(" rollback"),
+            Ok(("", TransactionCommand::Rollback))
+        );
+        assert!(parse_transaction("beginning").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use super::expression::scalar_value;
+use super::identifiers::{column_name, keyword, table_name, ws};
+use super::select::r#where;
+use super::spans::{spanned, Spans};
+use super::IResult;
+use crate::types::{Location, Update};
+
+use nom::{
+    bytes::complete::tag,
+    combinator::{cut, map},
+    error::context,
+    sequence::{pair, preceded, tuple},
+};
+
+// `update User set age = 28, name = "Egg" where age = 27`
+pub fn parse_update(input: &str) -> IResult<&str, Update> {
+    map(update_with_spans, |(update, _)| update)(input)
+}
+
+pub fn update_with_spans(input: &str) -> IResult<&str, (Update, Spans<'_>)> {
+    let parse_pair = pair(
+        column_name,
+        preceded(
+            cut(context("expected `=` after column name", ws(tag("=")))),
+            cut(context("expected a value after `=`", scalar_value)),
+        ),
+    );
+
+    map(
+        tuple((
+            preceded(
+                keyword("update"),
+                cut(context(
+                    "expected a table name after `update`",
+                    spanned(table_name),
+                )),
+            ),
+            preceded(
+                cut(context("expected `set` after table name", keyword("set"))),
+                cut(context(
+                    "expected `column = value` after `set`",
+                    nom::multi::separated_list1(ws(tag(",")), spanned(parse_pair)),
+                )),
+            ),
+            r#where,
+        )),
+        |((table, table_text), pairs, (r#where, where_spans))| {
+            let mut spans = vec![(Location::Table, table_text)];
+            let mut values = std::collections::BTreeMap::new();
+            for ((column, value), text) in pairs {
+                spans.push((Location::Column(column.clone()), text));
+                values.insert(column, value);
+            }
+            spans.extend(where_spans);
+            (
+                Update {
+                    table,
+                    values,
+                    r#where,
+                },
+                spans,
+            )
+        },
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_update;
+    use crate::{empty_where, equals, ColumnName, ScalarValue, TableName, Update};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_update() {
+        let mut values = BTreeMap::new();
+        values.insert(ColumnName("age".to_string()), ScalarValue::Int(28));
+        values.insert(
+            ColumnName("name".to_string()),
+            ScalarValue::String("Egg".to_string()),
+        );
+
+        assert_eq!(
+            parse_update("update User set age = 28, name = \"Egg\""),
+            Ok((
+                "",
+                Update {
+                    table: TableName("User".to_string()),
+                    values: values.clone(),
+                    r#where: empty_where()
+                }
+            ))
+        );
+        assert_eq!(
+            parse_update("update User set age = 28, name = \"Egg\" where age = 27"),
+            Ok((
+                "",
+                Update {
+                    table: TableName("User".to_string()),
+                    values,
+                    r#where: equals(ColumnName("age".to_string()), ScalarValue::Int(27))
+                }
+            ))
+        );
+    }
+}
@@ -0,0 +1,656 @@
+//! printing statements back out in the syntax they are parsed from
+use crate::typecheck::select::empty_where;
+use crate::types::{
+    write_columns, write_name, AlterTable, CatalogCommand, ColumnName, Columns, Comparison,
+    Constructor, Delete, Expression, Function, Insert, InsertValue, OnConflict, ScalarType,
+    ScalarValue, Select, SelectColumns, Statement, Table, TableChange, TableName,
+    TransactionCommand, Update,
+};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+// a name as it is written in a statement, quoted if it has to be
+struct Name<'a>(&'a str, fn(&char) -> bool);
+
+impl Display for Name<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_name(f, self.0, self.1)
+    }
+}
+
+fn table_name(table: &TableName) -> Name<'_> {
+    Name(&table.0, char::is_ascii_uppercase)
+}
+
+fn constructor_name(constructor: &Constructor) -> Name<'_> {
+    Name(&constructor.0, char::is_ascii_uppercase)
+}
+
+fn column_name(column: &ColumnName) -> Name<'_> {
+    Name(&column.0, char::is_ascii_lowercase)
+}
+
+fn write_table_name(f: &mut fmt::Formatter<'_>, table: &TableName) -> fmt::Result {
+    table_name(table).fmt(f)
+}
+
+fn write_constructor(f: &mut fmt::Formatter<'_>, constructor: &Constructor) -> fmt::Result {
+    constructor_name(constructor).fmt(f)
+}
+
+fn write_column_name(f: &mut fmt::Formatter<'_>, column: &ColumnName) -> fmt::Result {
+    column_name(column).fmt(f)
+}
+
+// `a, b, c`
+fn write_column_list(f: &mut fmt::Formatter<'_>, columns: &[ColumnName]) -> fmt::Result {
+    for (index, column) in columns.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write_column_name(f, column)?;
+    }
+    Ok(())
+}
+
+// `a = 1, b = 2` or `{ a: 1, b: 2 }`, depending on `separator`
+fn write_values(
+    f: &mut fmt::Formatter<'_>,
+    values: &BTreeMap<ColumnName, ScalarValue>,
+    separator: &str,
+) -> fmt::Result {
+    for (index, (column, value)) in values.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write_column_name(f, column)?;
+        write!(f, "{separator}{value}")?;
+    }
+    Ok(())
+}
+
+// the `where` clause is left out when it matches everything
+fn write_where(f: &mut fmt::Formatter<'_>, r#where: &Expression) -> fmt::Result {
+    if *r#where == empty_where() {
+        Ok(())
+    } else {
+        write!(f, " where {where}")
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Comparison(Comparison { column, value }) => {
+                write_column_name(f, column)?;
+                write!(f, " = {value}")
+            }
+            Expression::Bool(bool) => write!(f, "{bool}"),
+            Expression::BinaryFunction {
+                function,
+                expr_left,
+                expr_right,
+            } => write!(f, "{expr_left} {function} {expr_right}"),
+        }
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Function::And => write!(f, "&&"),
+        }
+    }
+}
+
+impl Display for Select {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "select ")?;
+        match &self.columns {
+            SelectColumns::SelectColumns { columns } => write_column_list(f, columns)?,
+            SelectColumns::SelectConstructor {
+                constructor,
+                columns,
+            } => {
+                write_constructor(f, constructor)?;
+                write!(f, " {{ ")?;
+                write_column_list(f, columns)?;
+                write!(f, " }}")?;
+            }
+        }
+        write!(f, " from ")?;
+        write_table_name(f, &self.table)?;
+        write_where(f, &self.r#where)
+    }
+}
+
+impl Display for Insert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "insert into ")?;
+        write_table_name(f, &self.table)?;
+        if let Some(key) = self.key {
+            write!(f, " key {key}")?;
+        }
+        let values = match &self.value {
+            InsertValue::Single { values } => values,
+            InsertValue::Multiple {
+                constructor,
+                values,
+            } => {
+                write!(f, " ")?;
+                write_constructor(f, constructor)?;
+                values
+            }
+        };
+        write!(f, " {{ ")?;
+        write_values(f, values, ": ")?;
+        write!(f, " }}")?;
+        if self.on_conflict == OnConflict::Replace {
+            write!(f, " on conflict replace")?;
+        }
+        if self.returning_key {
+            write!(f, " returning key")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Update {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "update ")?;
+        write_table_name(f, &self.table)?;
+        write!(f, " set ")?;
+        write_values(f, &self.values, " = ")?;
+        write_where(f, &self.r#where)
+    }
+}
+
+impl Display for Delete {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "delete from ")?;
+        write_table_name(f, &self.table)?;
+        write_where(f, &self.r#where)
+    }
+}
+
+impl Display for AlterTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "alter type ")?;
+        write_table_name(f, &self.table)?;
+        match &self.change {
+            TableChange::AddColumn {
+                column,
+                scalar_type,
+                default,
+            } => {
+                write!(f, " add column ")?;
+                write_column_name(f, column)?;
+                write!(f, ": {scalar_type} default {default}")
+            }
+            TableChange::DropColumn { column } => {
+                write!(f, " drop column ")?;
+                write_column_name(f, column)
+            }
+            TableChange::RenameColumn { from, to } => {
+                write!(f, " rename column ")?;
+                write_column_name(f, from)?;
+                write!(f, " to ")?;
+                write_column_name(f, to)
+            }
+            TableChange::AddConstructor {
+                constructor,
+                columns,
+                defaults,
+            } => {
+                write!(f, " add constructor ")?;
+                write_constructor(f, constructor)?;
+                write!(f, " ")?;
+                write_columns(f, columns, defaults)
+            }
+        }
+    }
+}
+
+impl Display for CatalogCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogCommand::ShowTables => write!(f, "show tables"),
+            CatalogCommand::Describe { table } => {
+                write!(f, "describe ")?;
+                write_table_name(f, table)
+            }
+            CatalogCommand::DropTable { table } => {
+                write!(f, "drop type ")?;
+                write_table_name(f, table)
+            }
+        }
+    }
+}
+
+impl Display for TransactionCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionCommand::Begin => write!(f, "begin"),
+            TransactionCommand::Commit => write!(f, "commit"),
+            TransactionCommand::Rollback => write!(f, "rollback"),
+        }
+    }
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Table(table) => table.fmt(f),
+            Statement::Select(select) => select.fmt(f),
+            Statement::Insert(insert) => insert.fmt(f),
+            Statement::Update(update) => update.fmt(f),
+            Statement::Delete(delete) => delete.fmt(f),
+            Statement::Alter(alter) => alter.fmt(f),
+            Statement::Catalog(catalog) => catalog.fmt(f),
+            Statement::Transaction(transaction) => transaction.fmt(f),
+        }
+    }
+}
+
+/// the statement on one line if it fits in `width` characters, otherwise
+/// with each column or value on a line of its own
+pub fn format_statement(statement: &Statement, width: usize) -> String {
+    let line = statement.to_string();
+    if line.chars().count() <= width {
+        return line;
+    }
+
+    match statement {
+        Statement::Table(table) => wrapped_table(table),
+        Statement::Select(select) => wrapped_select(select),
+        Statement::Insert(insert) => wrapped_insert(insert),
+        Statement::Update(update) => wrapped_update(update),
+        Statement::Delete(_)
+        | Statement::Alter(_)
+        | Statement::Catalog(_)
+        | Statement::Transaction(_) => line,
+    }
+}
+
+// one item to a line, indented by `depth` levels
+fn indented(items: impl IntoIterator<Item = String>, depth: usize) -> String {
+    let indent = "  ".repeat(depth);
+    items
+        .into_iter()
+        .map(|item| format!("{indent}{item}"))
+        .collect::<Vec<_>>()
+        .join(",\n")
+}
+
+fn wrapped_where(r#where: &Expression) -> String {
+    if *r#where == empty_where() {
+        String::new()
+    } else {
+        format!("\nwhere {where}")
+    }
+}
+
+fn wrapped_table(table: &Table) -> String {
+    let declarations = |constructor: Option<&Constructor>,
+                        columns: &BTreeMap<ColumnName, ScalarType>| {
+        let defaults = table.defaults.get(constructor);
+        columns
+            .iter()
+            .map(
+                |(column, scalar_type)| match defaults.and_then(|defaults| defaults.get(column)) {
+                    Some(default) => format!("{}: {scalar_type} = {default}", column_name(column)),
+                    None => format!("{}: {scalar_type}", column_name(column)),
+                },
+            )
+            .collect::<Vec<_>>()
+    };
+
+    let body = match &table.columns {
+        Columns::SingleConstructor(columns) => indented(declarations(None, columns), 1),
+        Columns::MultipleConstructors(constructors) => indented(
+            constructors.iter().map(|(constructor, columns)| {
+                format!(
+                    "{} {{\n{}\n  }}",
+                    constructor_name(constructor),
+                    indented(declarations(Some(constructor), columns), 2)
+                )
+            }),
+            1,
+        ),
+    };
+    let auto_increment = if table.auto_increment {
+        " autoincrement"
+    } else {
+        ""
+    };
+    format!(
+        "type {}{auto_increment} {{\n{body}\n}}",
+        table_name(&table.name)
+    )
+}
+
+fn wrapped_select(select: &Select) -> String {
+    let columns = match &select.columns {
+        SelectColumns::SelectColumns { columns } => format!(
+            "select\n{}",
+            indented(
+                columns.iter().map(|column| column_name(column).to_string()),
+                1
+            )
+        ),
+        SelectColumns::SelectConstructor {
+            constructor,
+            columns,
+        } => format!(
+            "select {} {{\n{}\n}}",
+            constructor_name(constructor),
+            indented(
+                columns.iter().map(|column| column_name(column).to_string()),
+                1
+            )
+        ),
+    };
+    format!(
+        "{columns}\nfrom {}{}",
+        table_name(&select.table),
+        wrapped_where(&select.r#where)
+    )
+}
+
+fn wrapped_insert(insert: &Insert) -> String {
+    let key = insert
+        .key
+        .map(|key| format!(" key {key}"))
+        .unwrap_or_default();
+    let (constructor, values) = match &insert.value {
+        InsertValue::Single { values } => (String::new(), values),
+        InsertValue::Multiple {
+            constructor,
+            values,
+        } => (format!(" {}", constructor_name(constructor)), values),
+    };
+    let values = values
+        .iter()
+        .map(|(column, value)| format!("{}: {value}", column_name(column)));
+    let on_conflict = match insert.on_conflict {
+        OnConflict::Replace => " on conflict replace",
+        OnConflict::Fail => "",
+    };
+    let returning_key = if insert.returning_key {
+        " returning key"
+    } else {
+        ""
+    };
+    format!(
+        "insert into {}{key}{constructor} {{\n{}\n}}{on_conflict}{returning_key}",
+        table_name(&insert.table),
+        indented(values, 1)
+    )
+}
+
+fn wrapped_update(update: &Update) -> String {
+    let values = update
+        .values
+        .iter()
+        .map(|(column, value)| format!("{} = {value}", column_name(column)));
+    format!(
+        "update {}\nset\n{}{}",
+        table_name(&update.table),
+        indented(values, 1),
+        wrapped_where(&update.r#where)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_statement;
+    use crate::types::KEYWORDS;
+    use crate::{
+        and, parse_statement, AlterTable, CatalogCommand, ColumnName, Columns, Comparison,
+        Constructor, Defaults, Delete, Expression, Insert, InsertValue, OnConflict, ScalarType,
+        ScalarValue, Select, SelectColumns, Statement, Table, TableChange, TableName,
+        TransactionCommand, Update,
+    };
+    use proptest::collection::{btree_map, vec};
+    use proptest::prelude::*;
+    use std::collections::BTreeMap;
+
+    fn keyword() -> impl Strategy<Value = String> {
+        proptest::sample::select(KEYWORDS).prop_map(ToString::to_string)
+    }
+
+    // plain names, and ones that have to be quoted
+    fn column_name() -> impl Strategy<Value = ColumnName> {
+        prop_oneof![
+            "[a-z][a-z0-9_]{0,6}",
+            "[A-Za-z ][A-Za-z0-9 `_]{0,6}",
+            keyword(),
+        ]
+        .prop_map(ColumnName)
+    }
+
+    fn upper_name() -> impl Strategy<Value = String> {
+        prop_oneof![
+            "[A-Z][A-Za-z0-9_]{0,6}",
+            "[a-z ][A-Za-z0-9 \"_]{0,6}",
+            // `Key` rather than `key`, so that it would parse without quotes
+            keyword().prop_map(|keyword| keyword[..1].to_uppercase() + &keyword[1..]),
+        ]
+    }
+
+    fn table_name() -> impl Strategy<Value = TableName> {
+        upper_name().prop_map(TableName)
+    }
+
+    fn scalar_type() -> impl Strategy<Value = ScalarType> {
+        prop_oneof![
+            Just(ScalarType::Bool),
+            Just(ScalarType::Int),
+            Just(ScalarType::String)
+        ]
+    }
+
+    fn scalar_value() -> impl Strategy<Value = ScalarValue> {
+        prop_oneof![
+            any::<bool>().prop_map(ScalarValue::Bool),
+            any::<i32>().prop_map(ScalarValue::Int),
+            ".{0,8}".prop_map(ScalarValue::String),
+        ]
+    }
+
+    fn comparison() -> impl Strategy<Value = Expression> {
+        prop_oneof![
+            any::<bool>().prop_map(Expression::Bool),
+            (column_name(), scalar_value())
+                .prop_map(|(column, value)| Expression::Comparison(Comparison { column, value })),
+        ]
+    }
+
+    // `&&` can only join two comparisons
+    fn expression() -> impl Strategy<Value = Expression> {
+        prop_oneof![
+            comparison(),
+            (comparison(), comparison()).prop_map(|(left, right)| and(left, right)),
+        ]
+    }
+
+    fn columns() -> impl Strategy<Value = BTreeMap<ColumnName, ScalarType>> {
+        btree_map(column_name(), scalar_type(), 1..4)
+    }
+
+    fn values() -> impl Strategy<Value = BTreeMap<ColumnName, ScalarValue>> {
+        btree_map(column_name(), scalar_value(), 1..4)
+    }
+
+    fn table() -> impl Strategy<Value = Table> {
+        let constructors = btree_map(upper_name().prop_map(Constructor), columns(), 1..3);
+        let columns = prop_oneof![
+            columns().prop_map(Columns::SingleConstructor),
+            constructors.prop_map(Columns::MultipleConstructors),
+        ];
+        (table_name(), columns, any::<bool>()).prop_map(|(name, columns, auto_increment)| Table {
+            name,
+            columns,
+            auto_increment,
+            defaults: Defaults::default(),
+        })
+    }
+
+    fn statement() -> impl Strategy<Value = Statement> {
+        let select_columns = prop_oneof![
+            vec(column_name(), 1..4).prop_map(|columns| SelectColumns::SelectColumns { columns }),
+            (upper_name(), vec(column_name(), 1..4)).prop_map(|(constructor, columns)| {
+                SelectColumns::SelectConstructor {
+                    constructor: Constructor(constructor),
+                    columns,
+                }
+            }),
+        ];
+        let select =
+            (table_name(), select_columns, expression()).prop_map(|(table, columns, r#where)| {
+                Statement::Select(Select {
+                    table,
+                    columns,
+                    r#where,
+                })
+            });
+
+        let insert_value = prop_oneof![
+            values().prop_map(|values| InsertValue::Single { values }),
+            (upper_name(), values()).prop_map(|(constructor, values)| {
+                InsertValue::Multiple {
+                    constructor: Constructor(constructor),
+                    values,
+                }
+            }),
+        ];
+        let insert = (
+            table_name(),
+            proptest::option::of(any::<i32>()),
+            insert_value,
+            any::<bool>(),
+            any::<bool>(),
+        )
+            .prop_map(|(table, key, value, replace, returning_key)| {
+                Statement::Insert(Insert {
+                    table,
+                    key,
+                    value,
+                    on_conflict: if replace {
+                        OnConflict::Replace
+                    } else {
+                        OnConflict::Fail
+                    },
+                    returning_key,
+                })
+            });
+
+        let update = (table_name(), values(), expression()).prop_map(|(table, values, r#where)| {
+            Statement::Update(Update {
+                table,
+                values,
+                r#where,
+            })
+        });
+        let delete = (table_name(), expression())
+            .prop_map(|(table, r#where)| Statement::Delete(Delete { table, r#where }));
+
+        let change = prop_oneof![
+            (column_name(), scalar_type(), scalar_value()).prop_map(
+                |(column, scalar_type, default)| TableChange::AddColumn {
+                    column,
+                    scalar_type,
+                    default,
+                }
+            ),
+            column_name().prop_map(|column| TableChange::DropColumn { column }),
+            (column_name(), column_name())
+                .prop_map(|(from, to)| TableChange::RenameColumn { from, to }),
+            (upper_name(), columns()).prop_map(|(constructor, columns)| {
+                TableChange::AddConstructor {
+                    constructor: Constructor(constructor),
+                    columns,
+                    defaults: BTreeMap::new(),
+                }
+            }),
+        ];
+        let alter = (table_name(), change)
+            .prop_map(|(table, change)| Statement::Alter(AlterTable { table, change }));
+
+        let catalog = prop_oneof![
+            Just(()).prop_map(|()| CatalogCommand::ShowTables),
+            table_name().prop_map(|table| CatalogCommand::Describe { table }),
+            table_name().prop_map(|table| CatalogCommand::DropTable { table }),
+        ];
+
+        let transaction = prop_oneof![
+            Just(TransactionCommand::Begin),
+            Just(TransactionCommand::Commit),
+            Just(TransactionCommand::Rollback),
+        ];
+
+        prop_oneof![
+            table().prop_map(Statement::Table),
+            select,
+            insert,
+            update,
+            delete,
+            alter,
+            catalog.prop_map(Statement::Catalog),
+            transaction.prop_map(Statement::Transaction),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn printed_statements_parse_again(statement in statement()) {
+            prop_assert_eq!(parse_statement(&statement.to_string()), Ok(statement));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn wrapped_statements_parse_again(statement in statement()) {
+            prop_assert_eq!(parse_statement(&format_statement(&statement, 0)), Ok(statement));
+        }
+    }
+
+    #[test]
+    fn test_wrapped_text() {
+        for source in [
+            "type Color {\n  Greyscale {\n    value: Int\n  },\n  RGB {\n    blue: Int,\n    red: Int = 0\n  }\n}",
+            "select RGB {\n  red,\n  green\n}\nfrom Color\nwhere red = 1",
+            "insert into User key 1 {\n  age: 27,\n  name: \"Egg\"\n} returning key",
+            "update User\nset\n  age = 28,\n  name = \"Egg\"",
+        ] {
+            let statement = parse_statement(source).expect("parse_statement");
+            assert_eq!(format_statement(&statement, 20), source);
+        }
+
+        let statement = parse_statement("select name from User").expect("parse_statement");
+        assert_eq!(format_statement(&statement, 80), "select name from User");
+    }
+
+    #[test]
+    fn test_canonical_text() {
+        for source in [
+            "select name, `Last Name` from User where age = 27 && nice = true",
+            "select RGB { red, green } from Color",
+            "insert into User key 1 Cat { name: \"Mr \\\"Cat\\\"\" } on conflict replace returning key",
+            "update User set age = 28, `true` = false where name = \"Egg\"",
+            "delete from User",
+            "insert into Lock `Key` { `from`: 1 }",
+            "select `Where` { `key` } from `Select`",
+            "alter type User add column email: String default \"\"",
+            "alter type User rename column email to contact",
+            "alter type Pet add constructor Fish { fins: Int = 0 }",
+            "show tables",
+            "drop type `user`",
+            "begin",
+            "rollback",
+        ] {
+            let statement = parse_statement(source).expect("parse_statement");
+            assert_eq!(statement.to_string(), source);
+        }
+    }
+}
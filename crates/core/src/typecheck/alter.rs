@@ -0,0 +1,286 @@
+use super::column::typecheck_column;
+use super::errors::single_error;
+use super::insert::get_table;
+use super::scalar::typecheck_scalar;
+use super::table::typecheck_table;
+use crate::types::{
+    AlterTable, ColumnName, Columns, Constructor, Location, ScalarType, Table, TableChange,
+    TableName, Type, TypeError, TypeErrors,
+};
+use std::collections::BTreeMap;
+
+// is this change allowed? if so, returns the table as it will be afterwards
+pub fn typecheck_alter(
+    tables: &BTreeMap<TableName, Table>,
+    alter: &AlterTable,
+) -> Result<Table, TypeErrors> {
+    let table =
+        get_table(tables, &alter.table).map_err(|error| single_error(Location::Table, error))?;
+
+    let new_table = apply_change(table, &alter.change)
+        .map_err(|(location, error)| single_error(location, error))?;
+
+    typecheck_table(&new_table)?;
+    Ok(new_table)
+}
+
+// there's only one change, so only one thing that can go wrong with it
+fn apply_change(table: &Table, change: &TableChange) -> Result<Table, (Location, TypeError)> {
+    let at = |location: Location| move |error| (location, error);
+    let mut new_table = table.clone();
+
+    match change {
+        TableChange::AddColumn {
+            column,
+            scalar_type,
+            default,
+        } => {
+            column_is_free(table, column).map_err(at(Location::Column(column.clone())))?;
+            typecheck_scalar(default, &Type::ScalarType(scalar_type.clone()))
+                .map_err(at(Location::Column(column.clone())))?;
+            for (constructor, columns) in constructor_columns(&mut new_table.columns) {
+                columns.insert(column.clone(), scalar_type.clone());
+                // rows inserted later get the same value as the existing ones
+                new_table
+                    .defaults
+                    .insert(constructor, column.clone(), default.clone());
+            }
+        }
+        TableChange::DropColumn { column } => {
+            typecheck_column(table, column).map_err(at(Location::Column(column.clone())))?;
+            for (_, columns) in constructor_columns(&mut new_table.columns) {
+                columns.remove(column);
+                // a constructor with no columns can't be declared, so don't make one
+                if columns.is_empty() {
+                    return Err((
+                        Location::Column(column.clone()),
+                        TypeError::DropOfLastColumn {
+                            table_name: table.name.clone(),
+                            column_name: column.clone(),
+                        },
+                    ));
+                }
+            }
+            new_table.defaults.remove(column);
+        }
+        TableChange::RenameColumn { from, to } => {
+            typecheck_column(table, from).map_err(at(Location::Column(from.clone())))?;
+            column_is_free(table, to).map_err(at(Location::Column(to.clone())))?;
+            for (_, columns) in constructor_columns(&mut new_table.columns) {
+                if let Some(scalar_type) = columns.remove(from) {
+                    columns.insert(to.clone(), scalar_type);
+                }
+            }
+            new_table.defaults.rename(from, to);
+        }
+        TableChange::AddConstructor {
+            constructor,
+            columns,
+            defaults,
+        } => {
+            let Columns::MultipleConstructors(constructors) = &mut new_table.columns else {
+                return Err((
+                    Location::Constructor,
+                    TypeError::ConstructorAddedToSingleConstructorTable {
+                        table: table.name.clone(),
+                    },
+                ));
+            };
+            if constructors.contains_key(constructor) {
+                return Err((
+                    Location::Constructor,
+                    TypeError::ConstructorAlreadyExists {
+                        table: table.name.clone(),
+                        constructor: constructor.clone(),
+                    },
+                ));
+            }
+            constructors.insert(constructor.clone(), columns.clone());
+            if !defaults.is_empty() {
+                new_table
+                    .defaults
+                    .constructors
+                    .insert(constructor.clone(), defaults.clone());
+            }
+
+            // shared columns must have the same type in every constructor
+            for column_name in columns.keys() {
+                typecheck_column(&new_table, column_name)
+                    .map_err(at(Location::Column(column_name.clone())))?;
+            }
+        }
+    }
+
+    Ok(new_table)
+}
+
+fn column_is_free(table: &Table, column_name: &ColumnName) -> Result<(), TypeError> {
+    match typecheck_column(table, column_name) {
+        Err(TypeError::ColumnNotFound { .. }) => Ok(()),
+        _ => Err(TypeError::ColumnAlreadyExists {
+            table_name: table.name.clone(),
+            column_name: column_name.clone(),
+        }),
+    }
+}
+
+/// the columns of each constructor, `None` for a table with a single constructor
+fn constructor_columns(
+    columns: &mut Columns,
+) -> Vec<(Option<&Constructor>, &mut BTreeMap<ColumnName, ScalarType>)> {
+    match columns {
+        Columns::SingleConstructor(columns) => vec![(None, columns)],
+        Columns::MultipleConstructors(constructors) => constructors
+            .iter_mut()
+            .map(|(constructor, columns)| (Some(constructor), columns))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::typecheck_alter;
+    use crate::types::{
+        AlterTable, ColumnName, Columns, Constructor, Defaults, Location, ScalarType, ScalarValue,
+        Table, TableChange, TableName, Type, TypeError, TypeErrors,
+    };
+    use std::collections::BTreeMap;
+
+    fn pet_tables() -> BTreeMap<TableName, Table> {
+        let mut cat_columns = BTreeMap::new();
+        cat_columns.insert(ColumnName("age".to_string()), ScalarType::Int);
+
+        let mut dog_columns = cat_columns.clone();
+        dog_columns.insert(ColumnName("likes_stick".to_string()), ScalarType::Bool);
+
+        let mut constructors = BTreeMap::new();
+        constructors.insert(Constructor("Cat".to_string()), cat_columns);
+        constructors.insert(Constructor("Dog".to_string()), dog_columns);
+
+        let table = Table {
+            name: TableName("Pet".to_string()),
+            columns: Columns::MultipleConstructors(constructors),
+            auto_increment: false,
+            defaults: Defaults::default(),
+        };
+
+        let mut tables = BTreeMap::new();
+        tables.insert(TableName("Pet".to_string()), table);
+        tables
+    }
+
+    #[test]
+    fn add_column_to_every_constructor() {
+        let alter = AlterTable {
+            table: TableName("Pet".to_string()),
+            change: TableChange::AddColumn {
+                column: ColumnName("name".to_string()),
+                scalar_type: ScalarType::String,
+                default: ScalarValue::String("Mr Pet".to_string()),
+            },
+        };
+
+        let Columns::MultipleConstructors(constructors) = typecheck_alter(&pet_tables(), &alter)
+            .expect("typecheck_alter")
+            .columns
+        else {
+            panic!("expected multiple constructors")
+        };
+
+        for columns in constructors.values() {
+            assert_eq!(
+                columns.get(&ColumnName("name".to_string())),
+                Some(&ScalarType::String)
+            );
+        }
+    }
+
+    #[test]
+    fn default_has_wrong_type() {
+        let alter = AlterTable {
+            table: TableName("Pet".to_string()),
+            change: TableChange::AddColumn {
+                column: ColumnName("name".to_string()),
+                scalar_type: ScalarType::String,
+                default: ScalarValue::Int(1),
+            },
+        };
+
+        assert_eq!(
+            typecheck_alter(&pet_tables(), &alter),
+            Err(TypeErrors(vec![(
+                Location::Column(ColumnName("name".to_string())),
+                TypeError::TypeMismatchInInput {
+                    expected_type: Type::ScalarType(ScalarType::String),
+                    input_value: ScalarValue::Int(1)
+                }
+            )]))
+        );
+    }
+
+    #[test]
+    fn rename_to_existing_column() {
+        let alter = AlterTable {
+            table: TableName("Pet".to_string()),
+            change: TableChange::RenameColumn {
+                from: ColumnName("age".to_string()),
+                to: ColumnName("likes_stick".to_string()),
+            },
+        };
+
+        assert_eq!(
+            typecheck_alter(&pet_tables(), &alter),
+            Err(TypeErrors(vec![(
+                Location::Column(ColumnName("likes_stick".to_string())),
+                TypeError::ColumnAlreadyExists {
+                    table_name: TableName("Pet".to_string()),
+                    column_name: ColumnName("likes_stick".to_string())
+                }
+            )]))
+        );
+    }
+
+    #[test]
+    fn drop_last_column() {
+        let alter = AlterTable {
+            table: TableName("Pet".to_string()),
+            change: TableChange::DropColumn {
+                column: ColumnName("age".to_string()),
+            },
+        };
+
+        assert_eq!(
+            typecheck_alter(&pet_tables(), &alter),
+            Err(TypeErrors(vec![(
+                Location::Column(ColumnName("age".to_string())),
+                TypeError::DropOfLastColumn {
+                    table_name: TableName("Pet".to_string()),
+                    column_name: ColumnName("age".to_string())
+                }
+            )]))
+        );
+    }
+
+    #[test]
+    fn add_constructor_with_clashing_column() {
+        let mut columns = BTreeMap::new();
+        columns.insert(ColumnName("age".to_string()), ScalarType::String);
+
+        let alter = AlterTable {
+            table: TableName("Pet".to_string()),
+            change: TableChange::AddConstructor {
+                constructor: Constructor("Fish".to_string()),
+                columns,
+                defaults: BTreeMap::new(),
+            },
+        };
+
+        let Err(TypeErrors(errors)) = typecheck_alter(&pet_tables(), &alter) else {
+            panic!("expected type errors")
+        };
+        assert!(matches!(
+            errors.as_slice(),
+            [(Location::Column(_), TypeError::ColumnMismatch { .. })]
+        ));
+    }
+}
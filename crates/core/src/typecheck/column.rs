@@ -14,8 +14,8 @@ pub fn typecheck_column(
         },
         Columns::MultipleConstructors(constructors) => {
             let mut matches: Vec<_> = constructors
-                .iter()
-                .filter_map(|(_constructor_name, columns)| columns.get(column_name))
+                .values()
+                .filter_map(|columns| columns.get(column_name))
                 .collect();
 
             if let Some(first) = matches.pop() {
@@ -26,7 +26,7 @@ pub fn typecheck_column(
                     } else {
                         // throw error, different types
                         Err(TypeError::ColumnMismatch {
-                            column_name: ColumnName("age".to_string()),
+                            column_name: column_name.clone(),
                             table_name: table.name.clone(),
                             left: first.clone(),
                             right: (*this_match).clone(),
@@ -59,7 +59,7 @@ pub fn typecheck_column(
 mod tests {
     use super::typecheck_column;
     use crate::types::{
-        ColumnName, Columns, Constructor, ScalarType, Table, TableName, Type, TypeError,
+        ColumnName, Columns, Constructor, Defaults, ScalarType, Table, TableName, Type, TypeError,
     };
     use std::collections::BTreeMap;
 
@@ -71,6 +71,8 @@ mod tests {
         let table = Table {
             name: TableName("User".to_string()),
             columns: Columns::SingleConstructor(columns),
+            auto_increment: false,
+            defaults: Defaults::default(),
         };
 
         assert_eq!(
@@ -93,6 +95,8 @@ mod tests {
         let table = Table {
             name: TableName("User".to_string()),
             columns: Columns::MultipleConstructors(constructors),
+            auto_increment: false,
+            defaults: Defaults::default(),
         };
 
         assert_eq!(
@@ -116,6 +120,8 @@ mod tests {
         let table = Table {
             name: TableName("User".to_string()),
             columns: Columns::MultipleConstructors(constructors),
+            auto_increment: false,
+            defaults: Defaults::default(),
         };
 
         assert_eq!(
@@ -142,6 +148,8 @@ mod tests {
         let table = Table {
             name: TableName("User".to_string()),
             columns: Columns::MultipleConstructors(constructors),
+            auto_increment: false,
+            defaults: Defaults::default(),
         };
 
         assert_eq!(
@@ -0,0 +1,61 @@
+use super::errors::{single_error, Errors};
+use super::expression::typecheck_expression;
+use super::insert::get_table;
+use crate::types::{Delete, Location, Table, TableName, TypeErrors};
+use std::collections::BTreeMap;
+
+// is this delete allowed?
+pub fn typecheck_delete(
+    tables: &BTreeMap<TableName, Table>,
+    delete: &Delete,
+) -> Result<(), TypeErrors> {
+    let table =
+        get_table(tables, &delete.table).map_err(|error| single_error(Location::Table, error))?;
+
+    let mut errors = Errors::default();
+    errors.extend(typecheck_expression(table, &delete.r#where));
+    errors.finish(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::typecheck_delete;
+    use crate::types::{
+        ColumnName, Columns, Defaults, Delete, Location, ScalarType, Table, TableName, TypeError,
+        TypeErrors,
+    };
+    use crate::{equals, ScalarValue};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn where_column_is_missing() {
+        let mut columns = BTreeMap::new();
+        columns.insert(ColumnName("age".to_string()), ScalarType::Int);
+
+        let table = Table {
+            name: TableName("Horses".to_string()),
+            columns: Columns::SingleConstructor(columns),
+            auto_increment: false,
+            defaults: Defaults::default(),
+        };
+
+        let mut tables = BTreeMap::new();
+        tables.insert(TableName("Horses".to_string()), table);
+
+        let delete = Delete {
+            table: TableName("Horses".to_string()),
+            r#where: equals(ColumnName("name".to_string()), ScalarValue::Int(1)),
+        };
+
+        assert_eq!(
+            typecheck_delete(&tables, &delete),
+            Err(TypeErrors(vec![(
+                Location::Where(ColumnName("name".to_string())),
+                TypeError::ColumnNotFound {
+                    table_name: TableName("Horses".to_string()),
+                    column_name: ColumnName("name".to_string())
+                }
+            )]))
+        );
+    }
+}
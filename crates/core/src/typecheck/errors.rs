@@ -0,0 +1,40 @@
+use crate::types::{Location, TypeError, TypeErrors};
+
+/// collects type errors as we go, so we can report all of them rather than
+/// stopping at the first one
+#[derive(Default)]
+pub struct Errors(Vec<(Location, TypeError)>);
+
+impl Errors {
+    pub fn push(&mut self, location: Location, error: TypeError) {
+        self.0.push((location, error));
+    }
+
+    /// keep the value if there is one, otherwise remember what went wrong
+    pub fn check<T>(&mut self, location: Location, result: Result<T, TypeError>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.push(location, error);
+                None
+            }
+        }
+    }
+
+    pub fn extend(&mut self, errors: Vec<(Location, TypeError)>) {
+        self.0.extend(errors);
+    }
+
+    pub fn finish<T>(self, value: T) -> Result<T, TypeErrors> {
+        if self.0.is_empty() {
+            Ok(value)
+        } else {
+            Err(TypeErrors(self.0))
+        }
+    }
+}
+
+/// for when there's no point carrying on
+pub fn single_error(location: Location, error: TypeError) -> TypeErrors {
+    TypeErrors(vec![(location, error)])
+}
@@ -0,0 +1,23 @@
+use super::column::typecheck_column;
+use crate::types::{Comparison, Expression, Location, Table, TypeError};
+
+// we don't 'learn' anything, just collect every problem, each at its comparison
+pub fn typecheck_expression(table: &Table, expression: &Expression) -> Vec<(Location, TypeError)> {
+    match expression {
+        Expression::Comparison(Comparison { column, .. }) => typecheck_column(table, column)
+            .err()
+            .map(|error| (Location::Where(column.clone()), error))
+            .into_iter()
+            .collect(),
+        Expression::BinaryFunction {
+            expr_left,
+            expr_right,
+            ..
+        } => {
+            let mut errors = typecheck_expression(table, expr_left);
+            errors.extend(typecheck_expression(table, expr_right));
+            errors
+        }
+        Expression::Bool(_) => vec![],
+    }
+}
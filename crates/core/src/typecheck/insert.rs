@@ -1,10 +1,12 @@
-use crate::typecheck::{column::typecheck_column, scalar::typecheck_scalar};
+use crate::typecheck::errors::{single_error, Errors};
+use crate::typecheck::scalar::typecheck_scalar;
 use crate::types::{
-    ColumnName, Columns, Insert, InsertValue, ScalarType, ScalarValue, Table, TableName, TypeError,
+    ColumnName, Columns, Constructor, Insert, InsertValue, Location, ScalarType, ScalarValue,
+    Table, TableName, Type, TypeError, TypeErrors,
 };
 use std::collections::BTreeMap;
 
-fn get_table<'a>(
+pub fn get_table<'a>(
     tables: &'a BTreeMap<TableName, Table>,
     table_name: &'a TableName,
 ) -> Result<&'a Table, TypeError> {
@@ -17,12 +19,31 @@ fn get_table<'a>(
 pub fn typecheck_insert(
     tables: &BTreeMap<TableName, Table>,
     insert: &Insert,
-) -> Result<(), TypeError> {
-    let table = get_table(tables, &insert.table)?;
+) -> Result<(), TypeErrors> {
+    let table =
+        get_table(tables, &insert.table).map_err(|error| single_error(Location::Table, error))?;
 
-    match (&insert.value, &table.columns) {
+    let mut errors = Errors::default();
+
+    match (insert.key, table.auto_increment) {
+        (None, false) => errors.push(
+            Location::Key,
+            TypeError::KeyNotSpecified {
+                table: table.name.clone(),
+            },
+        ),
+        (Some(_), true) => errors.push(
+            Location::Key,
+            TypeError::KeySpecifiedButGenerated {
+                table: table.name.clone(),
+            },
+        ),
+        _ => {}
+    }
+
+    let constructor_columns = match (&insert.value, &table.columns) {
         (InsertValue::Single { values }, Columns::SingleConstructor(columns)) => {
-            check_values_against_column(table, columns, values)
+            Ok((None, columns, values))
         }
         (
             InsertValue::Multiple {
@@ -30,10 +51,13 @@ pub fn typecheck_insert(
                 values,
             },
             Columns::MultipleConstructors(constructors),
-        ) => {
-            let columns = constructors.get(constructor).unwrap();
-            check_values_against_column(table, columns, values)
-        }
+        ) => constructors
+            .get(constructor)
+            .map(|columns| (Some(constructor), columns, values))
+            .ok_or_else(|| TypeError::ConstructorNotFound {
+                table: table.name.clone(),
+                constructor: constructor.clone(),
+            }),
         (InsertValue::Single { .. }, Columns::MultipleConstructors(_)) => {
             Err(TypeError::ConstructorNotSpecified {
                 table: table.name.clone(),
@@ -44,33 +68,69 @@ pub fn typecheck_insert(
                 table: table.name.clone(),
             })
         }
+    };
+
+    if let Some((constructor, columns, values)) =
+        errors.check(Location::Constructor, constructor_columns)
+    {
+        check_values_against_column(table, constructor, columns, values, &mut errors);
     }
+
+    errors.finish(())
 }
 
 fn check_values_against_column(
     table: &Table,
+    constructor: Option<&Constructor>,
     columns: &BTreeMap<ColumnName, ScalarType>,
     values: &BTreeMap<ColumnName, ScalarValue>,
-) -> Result<(), TypeError> {
-    for column_name in columns.keys() {
-        let (_, column_type) = typecheck_column(table, column_name)?;
-        let value = values
-            .get(column_name)
-            .ok_or_else(|| TypeError::MissingColumnInInput {
+    errors: &mut Errors,
+) {
+    // anything we don't know about would be stored and then never read again
+    let unexpected: Vec<_> = values
+        .keys()
+        .filter(|column_name| !columns.contains_key(column_name))
+        .cloned()
+        .collect();
+    if !unexpected.is_empty() {
+        errors.push(
+            Location::Values,
+            TypeError::UnexpectedColumnsInInput {
+                table_name: table.name.clone(),
+                column_names: unexpected,
+            },
+        );
+    }
+
+    // the row is stored with this constructor's own types, so a column that is
+    // optional across the table because another constructor lacks it is still
+    // required here
+    for (column_name, scalar_type) in columns {
+        let result = match values.get(column_name) {
+            Some(value) => typecheck_scalar(value, &Type::ScalarType(scalar_type.clone())),
+            // the engine fills in this constructor's defaults for missing columns
+            None if table
+                .defaults
+                .get(constructor)
+                .is_some_and(|defaults| defaults.contains_key(column_name)) =>
+            {
+                Ok(())
+            }
+            None => Err(TypeError::MissingColumnInInput {
                 column_name: column_name.clone(),
                 table_name: table.name.clone(),
-            })?;
-        typecheck_scalar(value, &column_type)?;
+            }),
+        };
+        errors.check(Location::Column(column_name.clone()), result);
     }
-    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::{typecheck_insert, BTreeMap};
     use crate::types::{
-        ColumnName, Columns, Constructor, Insert, InsertValue, ScalarType, ScalarValue, Table,
-        TableName, Type, TypeError,
+        ColumnName, Columns, Constructor, Defaults, Insert, InsertValue, Location, OnConflict,
+        ScalarType, ScalarValue, Table, TableName, Type, TypeError, TypeErrors,
     };
 
     #[test]
@@ -79,15 +139,98 @@ mod tests {
 
         let insert = Insert {
             table: TableName("Horses".to_string()),
-            key: 100,
+            key: Some(100),
             value: InsertValue::Single {
                 values: BTreeMap::new(),
             },
+            on_conflict: OnConflict::Fail,
+            returning_key: false,
+        };
+
+        assert_eq!(
+            typecheck_insert(&tables, &insert),
+            Err(TypeErrors(vec![(
+                Location::Table,
+                TypeError::TableNotFound(TableName("Horses".to_string()))
+            )]))
+        );
+    }
+
+    #[test]
+    fn key_is_missing() {
+        let mut columns = BTreeMap::new();
+        columns.insert(ColumnName("age".to_string()), ScalarType::Int);
+
+        let table = Table {
+            name: TableName("Horses".to_string()),
+            columns: Columns::SingleConstructor(columns),
+            auto_increment: false,
+            defaults: Defaults::default(),
+        };
+
+        let mut tables = BTreeMap::new();
+        tables.insert(TableName("Horses".to_string()), table);
+
+        let mut insert_value = BTreeMap::new();
+        insert_value.insert(ColumnName("age".to_string()), ScalarValue::Int(1));
+
+        let insert = Insert {
+            table: TableName("Horses".to_string()),
+            key: None,
+            value: InsertValue::Single {
+                values: insert_value,
+            },
+            on_conflict: OnConflict::Fail,
+            returning_key: false,
         };
 
         assert_eq!(
             typecheck_insert(&tables, &insert),
-            Err(TypeError::TableNotFound(TableName("Horses".to_string())))
+            Err(TypeErrors(vec![(
+                Location::Key,
+                TypeError::KeyNotSpecified {
+                    table: TableName("Horses".to_string())
+                }
+            )]))
+        );
+    }
+
+    #[test]
+    fn key_is_generated() {
+        let mut columns = BTreeMap::new();
+        columns.insert(ColumnName("age".to_string()), ScalarType::Int);
+
+        let table = Table {
+            name: TableName("Horses".to_string()),
+            columns: Columns::SingleConstructor(columns),
+            auto_increment: true,
+            defaults: Defaults::default(),
+        };
+
+        let mut tables = BTreeMap::new();
+        tables.insert(TableName("Horses".to_string()), table);
+
+        let mut insert_value = BTreeMap::new();
+        insert_value.insert(ColumnName("age".to_string()), ScalarValue::Int(1));
+
+        let insert = Insert {
+            table: TableName("Horses".to_string()),
+            key: Some(100),
+            value: InsertValue::Single {
+                values: insert_value,
+            },
+            on_conflict: OnConflict::Fail,
+            returning_key: false,
+        };
+
+        assert_eq!(
+            typecheck_insert(&tables, &insert),
+            Err(TypeErrors(vec![(
+                Location::Key,
+                TypeError::KeySpecifiedButGenerated {
+                    table: TableName("Horses".to_string())
+                }
+            )]))
         );
     }
 
@@ -99,6 +242,8 @@ mod tests {
         let table = Table {
             name: TableName("Horses".to_string()),
             columns: Columns::SingleConstructor(columns),
+            auto_increment: false,
+            defaults: Defaults::default(),
         };
 
         let mut tables = BTreeMap::new();
@@ -106,18 +251,23 @@ mod tests {
 
         let insert = Insert {
             table: TableName("Horses".to_string()),
-            key: 100,
+            key: Some(100),
             value: InsertValue::Single {
                 values: BTreeMap::new(),
             },
+            on_conflict: OnConflict::Fail,
+            returning_key: false,
         };
 
         assert_eq!(
             typecheck_insert(&tables, &insert),
-            Err(TypeError::MissingColumnInInput {
-                table_name: TableName("Horses".to_string()),
-                column_name: ColumnName("age".to_string())
-            })
+            Err(TypeErrors(vec![(
+                Location::Column(ColumnName("age".to_string())),
+                TypeError::MissingColumnInInput {
+                    table_name: TableName("Horses".to_string()),
+                    column_name: ColumnName("age".to_string())
+                }
+            )]))
         );
     }
 
@@ -129,6 +279,8 @@ mod tests {
         let table = Table {
             name: TableName("Horses".to_string()),
             columns: Columns::SingleConstructor(columns),
+            auto_increment: false,
+            defaults: Defaults::default(),
         };
 
         let mut tables = BTreeMap::new();
@@ -143,18 +295,23 @@ mod tests {
 
         let insert = Insert {
             table: TableName("Horses".to_string()),
-            key: 100,
+            key: Some(100),
             value: InsertValue::Single {
                 values: insert_value,
             },
+            on_conflict: OnConflict::Fail,
+            returning_key: false,
         };
 
         assert_eq!(
             typecheck_insert(&tables, &insert),
-            Err(TypeError::TypeMismatchInInput {
-                expected_type: Type::ScalarType(ScalarType::Int),
-                input_value: ScalarValue::String("dog".to_string())
-            })
+            Err(TypeErrors(vec![(
+                Location::Column(ColumnName("age".to_string())),
+                TypeError::TypeMismatchInInput {
+                    expected_type: Type::ScalarType(ScalarType::Int),
+                    input_value: ScalarValue::String("dog".to_string())
+                }
+            )]))
         );
     }
 
@@ -173,6 +330,8 @@ mod tests {
         let table = Table {
             name: TableName("Horses".to_string()),
             columns: Columns::MultipleConstructors(constructors),
+            auto_increment: false,
+            defaults: Defaults::default(),
         };
 
         let mut tables = BTreeMap::new();
@@ -180,19 +339,211 @@ mod tests {
 
         let insert = Insert {
             table: TableName("Horses".to_string()),
-            key: 100,
+            key: Some(100),
             value: InsertValue::Multiple {
                 constructor: Constructor("Age".to_string()),
                 values: BTreeMap::new(),
             },
+            on_conflict: OnConflict::Fail,
+            returning_key: false,
         };
 
         assert_eq!(
             typecheck_insert(&tables, &insert),
-            Err(TypeError::MissingColumnInInput {
-                table_name: TableName("Horses".to_string()),
-                column_name: ColumnName("age".to_string())
-            })
+            Err(TypeErrors(vec![(
+                Location::Column(ColumnName("age".to_string())),
+                TypeError::MissingColumnInInput {
+                    table_name: TableName("Horses".to_string()),
+                    column_name: ColumnName("age".to_string())
+                }
+            )]))
+        );
+    }
+
+    #[test]
+    fn null_for_a_column_the_constructor_requires() {
+        // `name` is optional across the table, as `Fish` doesn't have it
+        let (_, table) =
+            crate::parse_table("type Pet { Cat { name: String }, Fish { fins: Int } }")
+                .expect("parse_table");
+
+        let mut tables = BTreeMap::new();
+        tables.insert(TableName("Pet".to_string()), table);
+
+        let mut values = BTreeMap::new();
+        values.insert(ColumnName("name".to_string()), ScalarValue::Null);
+
+        let insert = Insert {
+            table: TableName("Pet".to_string()),
+            key: Some(1),
+            value: InsertValue::Multiple {
+                constructor: Constructor("Cat".to_string()),
+                values,
+            },
+            on_conflict: OnConflict::Fail,
+            returning_key: false,
+        };
+
+        assert_eq!(
+            typecheck_insert(&tables, &insert),
+            Err(TypeErrors(vec![(
+                Location::Column(ColumnName("name".to_string())),
+                TypeError::TypeMismatchInInput {
+                    expected_type: Type::ScalarType(ScalarType::String),
+                    input_value: ScalarValue::Null
+                }
+            )]))
+        );
+    }
+
+    #[test]
+    fn column_has_default() {
+        let (_, table) =
+            crate::parse_table("type Horses { age: Int = 0, name: String }").expect("parse_table");
+
+        let mut tables = BTreeMap::new();
+        tables.insert(TableName("Horses".to_string()), table);
+
+        let mut insert_value = BTreeMap::new();
+        insert_value.insert(
+            ColumnName("name".to_string()),
+            ScalarValue::String("Horse".to_string()),
+        );
+
+        let insert = Insert {
+            table: TableName("Horses".to_string()),
+            key: Some(100),
+            value: InsertValue::Single {
+                values: insert_value,
+            },
+            on_conflict: OnConflict::Fail,
+            returning_key: false,
+        };
+
+        assert_eq!(typecheck_insert(&tables, &insert), Ok(()));
+    }
+
+    #[test]
+    fn default_from_another_constructor() {
+        let (_, table) = crate::parse_table(
+            "type Pet { Cat { age: Int = 0, name: String }, Dog { age: Int, name: String } }",
+        )
+        .expect("parse_table");
+
+        let mut tables = BTreeMap::new();
+        tables.insert(TableName("Pet".to_string()), table);
+
+        let (_, cat) = crate::parse_insert("insert into Pet key 1 Cat { name: \"Mr Cat\" }")
+            .expect("parse_insert");
+        assert_eq!(typecheck_insert(&tables, &cat), Ok(()));
+
+        // only `Cat` declared a default for `age`
+        let (_, dog) = crate::parse_insert("insert into Pet key 2 Dog { name: \"Mr Dog\" }")
+            .expect("parse_insert");
+        assert_eq!(
+            typecheck_insert(&tables, &dog),
+            Err(TypeErrors(vec![(
+                Location::Column(ColumnName("age".to_string())),
+                TypeError::MissingColumnInInput {
+                    column_name: ColumnName("age".to_string()),
+                    table_name: TableName("Pet".to_string())
+                }
+            )]))
+        );
+    }
+
+    #[test]
+    fn unexpected_columns() {
+        let (_, table) = crate::parse_table("type Horses { age: Int }").expect("parse_table");
+
+        let mut tables = BTreeMap::new();
+        tables.insert(TableName("Horses".to_string()), table);
+
+        let (_, insert) = crate::parse_insert(
+            "insert into Horses key 1 { age: 1, name: \"Horse\", colour: \"brown\" }",
+        )
+        .expect("parse_insert");
+
+        assert_eq!(
+            typecheck_insert(&tables, &insert),
+            Err(TypeErrors(vec![(
+                Location::Values,
+                TypeError::UnexpectedColumnsInInput {
+                    table_name: TableName("Horses".to_string()),
+                    column_names: vec![
+                        ColumnName("colour".to_string()),
+                        ColumnName("name".to_string())
+                    ]
+                }
+            )]))
+        );
+    }
+
+    #[test]
+    fn unknown_constructor() {
+        let (_, table) = crate::parse_table("type Pet { Cat { age: Int }, Dog { age: Int } }")
+            .expect("parse_table");
+
+        let mut tables = BTreeMap::new();
+        tables.insert(TableName("Pet".to_string()), table);
+
+        let (_, insert) =
+            crate::parse_insert("insert into Pet key 1 Horse { age: 1 }").expect("parse_insert");
+
+        assert_eq!(
+            typecheck_insert(&tables, &insert),
+            Err(TypeErrors(vec![(
+                Location::Constructor,
+                TypeError::ConstructorNotFound {
+                    table: TableName("Pet".to_string()),
+                    constructor: Constructor("Horse".to_string())
+                }
+            )]))
+        );
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let (_, table) = crate::parse_table("type Horses { age: Int, name: String, nice: Bool }")
+            .expect("parse_table");
+
+        let mut tables = BTreeMap::new();
+        tables.insert(TableName("Horses".to_string()), table);
+
+        let (_, insert) =
+            crate::parse_insert("insert into Horses { age: true, nice: 1 }").expect("parse_insert");
+
+        assert_eq!(
+            typecheck_insert(&tables, &insert),
+            Err(TypeErrors(vec![
+                (
+                    Location::Key,
+                    TypeError::KeyNotSpecified {
+                        table: TableName("Horses".to_string())
+                    }
+                ),
+                (
+                    Location::Column(ColumnName("age".to_string())),
+                    TypeError::TypeMismatchInInput {
+                        expected_type: Type::ScalarType(ScalarType::Int),
+                        input_value: ScalarValue::Bool(true)
+                    }
+                ),
+                (
+                    Location::Column(ColumnName("name".to_string())),
+                    TypeError::MissingColumnInInput {
+                        table_name: TableName("Horses".to_string()),
+                        column_name: ColumnName("name".to_string())
+                    }
+                ),
+                (
+                    Location::Column(ColumnName("nice".to_string())),
+                    TypeError::TypeMismatchInInput {
+                        expected_type: Type::ScalarType(ScalarType::Bool),
+                        input_value: ScalarValue::Int(1)
+                    }
+                )
+            ]))
         );
     }
 }
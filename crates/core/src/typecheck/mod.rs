@@ -1,4 +1,10 @@
+pub mod alter;
 mod column;
+pub mod delete;
+mod errors;
+mod expression;
 pub mod insert;
 mod scalar;
 pub mod select;
+pub mod table;
+pub mod update;
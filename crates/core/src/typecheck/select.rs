@@ -1,7 +1,8 @@
 use super::column::typecheck_column;
+use super::errors::Errors;
+use super::expression::typecheck_expression;
 use crate::types::{
-    ColumnName, Columns, Comparison, Expression, Select, SelectColumns, Table, TableName, Type,
-    TypeError,
+    ColumnName, Expression, Location, Select, SelectColumns, Table, TableName, Type, TypeErrors,
 };
 use std::collections::BTreeMap;
 
@@ -13,7 +14,7 @@ pub fn empty_where() -> Expression {
 pub fn typecheck_select(
     tables: &BTreeMap<TableName, Table>,
     select: &Select,
-) -> Result<Vec<(ColumnName, Type)>, TypeError> {
+) -> Result<Vec<(ColumnName, Type)>, TypeErrors> {
     // this should already be there
     let table = tables.get(&select.table).unwrap();
 
@@ -22,42 +23,19 @@ pub fn typecheck_select(
         | SelectColumns::SelectConstructor { columns, .. } => columns,
     };
 
-    let typed_columns: Vec<(ColumnName, Type)> =
-        select_columns
-            .iter()
-            .try_fold(Vec::new(), |mut acc, column| {
-                let res = typecheck_column(table, column)?;
-                acc.push(res);
-                Ok(acc)
-            })?;
+    let mut errors = Errors::default();
 
-    typecheck_expression(table, &select.r#where)?;
+    let typed_columns: Vec<(ColumnName, Type)> = select_columns
+        .iter()
+        .filter_map(|column| {
+            errors.check(
+                Location::Column(column.clone()),
+                typecheck_column(table, column),
+            )
+        })
+        .collect();
 
-    Ok(typed_columns)
-}
+    errors.extend(typecheck_expression(table, &select.r#where));
 
-// we don't 'learn' anything, just explode or don't
-fn typecheck_expression(table: &Table, expression: &Expression) -> Result<(), TypeError> {
-    match expression {
-        Expression::Comparison(Comparison { column, .. }) => match &table.columns {
-            Columns::SingleConstructor(columns) => match columns.get(column) {
-                Some(_) => Ok(()),
-                None => Err(TypeError::ColumnNotFound {
-                    column_name: column.clone(),
-                    table_name: table.name.clone(),
-                }),
-            },
-            Columns::MultipleConstructors(_) => todo!("multiple constructors"),
-        },
-        Expression::BinaryFunction {
-            expr_left,
-            expr_right,
-            ..
-        } => {
-            typecheck_expression(table, expr_left)?;
-            typecheck_expression(table, expr_right)?;
-            Ok(())
-        }
-        Expression::Bool(_) => Ok(()),
-    }
+    errors.finish(typed_columns)
 }
@@ -0,0 +1,104 @@
+use super::errors::Errors;
+use super::scalar::typecheck_scalar;
+use crate::types::{Columns, Location, Table, Type, TypeError, TypeErrors, MAX_CONSTRUCTORS};
+
+// is this table definition allowed?
+pub fn typecheck_table(table: &Table) -> Result<(), TypeErrors> {
+    let mut errors = Errors::default();
+    if let Columns::MultipleConstructors(constructors) = &table.columns {
+        if constructors.len() > MAX_CONSTRUCTORS {
+            errors.push(
+                Location::Table,
+                TypeError::TooManyConstructors {
+                    table: table.name.clone(),
+                    count: constructors.len(),
+                },
+            );
+        }
+    }
+    let defaults = std::iter::once((None, &table.defaults.columns)).chain(
+        table
+            .defaults
+            .constructors
+            .iter()
+            .map(|(constructor, defaults)| (Some(constructor), defaults)),
+    );
+    for (constructor, defaults) in defaults {
+        // each default is checked against the constructor it was declared in
+        let columns = match (&table.columns, constructor) {
+            (Columns::SingleConstructor(columns), None) => Some(columns),
+            (Columns::MultipleConstructors(constructors), Some(constructor)) => {
+                constructors.get(constructor)
+            }
+            _ => None,
+        };
+        for (column_name, default) in defaults {
+            let result = columns
+                .and_then(|columns| columns.get(column_name))
+                .ok_or_else(|| TypeError::ColumnNotFound {
+                    table_name: table.name.clone(),
+                    column_name: column_name.clone(),
+                })
+                .and_then(|scalar_type| {
+                    typecheck_scalar(default, &Type::ScalarType(scalar_type.clone()))
+                });
+            errors.check(Location::Column(column_name.clone()), result);
+        }
+    }
+    errors.finish(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::typecheck_table;
+    use crate::types::{
+        ColumnName, Location, ScalarType, ScalarValue, TableName, Type, TypeError, TypeErrors,
+    };
+
+    #[test]
+    fn default_has_wrong_type() {
+        let (_, table) =
+            crate::parse_table("type User { age: Int = \"old\" }").expect("parse_table");
+
+        assert_eq!(
+            typecheck_table(&table),
+            Err(TypeErrors(vec![(
+                Location::Column(ColumnName("age".to_string())),
+                TypeError::TypeMismatchInInput {
+                    expected_type: Type::ScalarType(ScalarType::Int),
+                    input_value: ScalarValue::String("old".to_string())
+                }
+            )]))
+        );
+    }
+
+    #[test]
+    fn defaults_match() {
+        let (_, table) = crate::parse_table(
+            "type Pet { Cat { age: Int = 0 }, Dog { age: Int, likes_stick: Bool = true } }",
+        )
+        .expect("parse_table");
+
+        assert_eq!(typecheck_table(&table), Ok(()));
+    }
+
+    #[test]
+    fn too_many_constructors() {
+        let constructors: Vec<String> = (0..=256)
+            .map(|index| format!("C{index} {{ age: Int }}"))
+            .collect();
+        let source = format!("type Pet {{ {} }}", constructors.join(", "));
+        let (_, table) = crate::parse_table(&source).expect("parse_table");
+
+        assert_eq!(
+            typecheck_table(&table),
+            Err(TypeErrors(vec![(
+                Location::Table,
+                TypeError::TooManyConstructors {
+                    table: TableName("Pet".to_string()),
+                    count: 257
+                }
+            )]))
+        );
+    }
+}
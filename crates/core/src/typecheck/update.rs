@@ -0,0 +1,127 @@
+use super::column::typecheck_column;
+use super::errors::{single_error, Errors};
+use super::expression::typecheck_expression;
+use super::insert::get_table;
+use super::scalar::typecheck_scalar;
+use crate::types::{Location, Table, TableName, Type, TypeError, TypeErrors, Update};
+use std::collections::BTreeMap;
+
+// is this update allowed?
+pub fn typecheck_update(
+    tables: &BTreeMap<TableName, Table>,
+    update: &Update,
+) -> Result<(), TypeErrors> {
+    let table =
+        get_table(tables, &update.table).map_err(|error| single_error(Location::Table, error))?;
+
+    let mut errors = Errors::default();
+
+    for (column_name, value) in &update.values {
+        let result = match typecheck_column(table, column_name) {
+            // setting a column some constructors don't have would break their rows
+            Ok((_, Type::Optional(_))) => Err(TypeError::UpdateOfPartialColumn {
+                table_name: table.name.clone(),
+                column_name: column_name.clone(),
+            }),
+            Ok((_, column_type)) => typecheck_scalar(value, &column_type),
+            Err(error) => Err(error),
+        };
+        errors.check(Location::Column(column_name.clone()), result);
+    }
+
+    errors.extend(typecheck_expression(table, &update.r#where));
+    errors.finish(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::typecheck_update;
+    use crate::empty_where;
+    use crate::types::{
+        ColumnName, Columns, Constructor, Defaults, Location, ScalarType, ScalarValue, Table,
+        TableName, Type, TypeError, TypeErrors, Update,
+    };
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn value_has_wrong_type() {
+        let mut columns = BTreeMap::new();
+        columns.insert(ColumnName("age".to_string()), ScalarType::Int);
+
+        let table = Table {
+            name: TableName("Horses".to_string()),
+            columns: Columns::SingleConstructor(columns),
+            auto_increment: false,
+            defaults: Defaults::default(),
+        };
+
+        let mut tables = BTreeMap::new();
+        tables.insert(TableName("Horses".to_string()), table);
+
+        let mut values = BTreeMap::new();
+        values.insert(ColumnName("age".to_string()), ScalarValue::Bool(true));
+
+        let update = Update {
+            table: TableName("Horses".to_string()),
+            values,
+            r#where: empty_where(),
+        };
+
+        assert_eq!(
+            typecheck_update(&tables, &update),
+            Err(TypeErrors(vec![(
+                Location::Column(ColumnName("age".to_string())),
+                TypeError::TypeMismatchInInput {
+                    expected_type: Type::ScalarType(ScalarType::Int),
+                    input_value: ScalarValue::Bool(true)
+                }
+            )]))
+        );
+    }
+
+    #[test]
+    fn column_not_in_every_constructor() {
+        let mut cat_columns = BTreeMap::new();
+        cat_columns.insert(ColumnName("age".to_string()), ScalarType::Int);
+
+        let mut dog_columns = cat_columns.clone();
+        dog_columns.insert(ColumnName("likes_stick".to_string()), ScalarType::Bool);
+
+        let mut constructors = BTreeMap::new();
+        constructors.insert(Constructor("Cat".to_string()), cat_columns);
+        constructors.insert(Constructor("Dog".to_string()), dog_columns);
+
+        let table = Table {
+            name: TableName("Pet".to_string()),
+            columns: Columns::MultipleConstructors(constructors),
+            auto_increment: false,
+            defaults: Defaults::default(),
+        };
+
+        let mut tables = BTreeMap::new();
+        tables.insert(TableName("Pet".to_string()), table);
+
+        let mut values = BTreeMap::new();
+        values.insert(
+            ColumnName("likes_stick".to_string()),
+            ScalarValue::Bool(true),
+        );
+
+        let update = Update {
+            table: TableName("Pet".to_string()),
+            values,
+            r#where: empty_where(),
+        };
+
+        assert_eq!(
+            typecheck_update(&tables, &update),
+            Err(TypeErrors(vec![(
+                Location::Column(ColumnName("likes_stick".to_string())),
+                TypeError::UpdateOfPartialColumn {
+                    table_name: TableName("Pet".to_string()),
+                    column_name: ColumnName("likes_stick".to_string())
+                }
+            )]))
+        );
+    }
+}
@@ -64,8 +64,94 @@ pub enum InsertValue {
 #[derive(Debug, PartialEq)]
 pub struct Insert {
     pub table: TableName,
-    pub key: i32,
+    /// `None` when the table generates its own keys
+    pub key: Option<i32>,
     pub value: InsertValue,
+    pub on_conflict: OnConflict,
+    pub returning_key: bool,
+}
+
+/// what to do when a row already exists with the key we are inserting
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnConflict {
+    #[default]
+    Fail,
+    Replace,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Delete {
+    pub table: TableName,
+    pub r#where: Expression,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Update {
+    pub table: TableName,
+    pub values: BTreeMap<ColumnName, ScalarValue>,
+    pub r#where: Expression,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AlterTable {
+    pub table: TableName,
+    pub change: TableChange,
+}
+
+/// changes apply to every constructor that has the column
+#[derive(Debug, PartialEq)]
+pub enum TableChange {
+    /// `add column email: String default ""`
+    AddColumn {
+        column: ColumnName,
+        scalar_type: ScalarType,
+        /// stored in every existing row
+        default: ScalarValue,
+    },
+    /// `drop column email`
+    DropColumn { column: ColumnName },
+    /// `rename column email to contact`
+    RenameColumn { from: ColumnName, to: ColumnName },
+    /// `add constructor Fish { fins: Int, name: String = "Fish" }`
+    AddConstructor {
+        constructor: Constructor,
+        columns: BTreeMap<ColumnName, ScalarType>,
+        defaults: BTreeMap<ColumnName, ScalarValue>,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CatalogCommand {
+    /// `show tables`
+    ShowTables,
+    /// `describe User`
+    Describe { table: TableName },
+    /// `drop type User`
+    DropTable { table: TableName },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionCommand {
+    /// `begin`
+    Begin,
+    /// `commit`
+    Commit,
+    /// `rollback`
+    Rollback,
+}
+
+/// anything that can be written as a query
+#[derive(Debug, PartialEq)]
+pub enum Statement {
+    /// `type User { name: String }`
+    Table(Table),
+    Select(Select),
+    Insert(Insert),
+    Update(Update),
+    Delete(Delete),
+    Alter(AlterTable),
+    Catalog(CatalogCommand),
+    Transaction(TransactionCommand),
 }
 
 #[derive(Debug, PartialEq)]
@@ -106,18 +192,140 @@ pub fn bool_expr(bool: bool) -> Expression {
     Expression::Bool(bool)
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(thiserror::Error, Debug, PartialEq)]
 pub enum SelectError {
-    TypeError(TypeError),
+    #[error("{0}")]
+    TypeError(TypeErrors),
+    #[error("table not found: {0}")]
     TableNotFound(TableName),
 }
 
 #[derive(thiserror::Error, Debug, PartialEq)]
 pub enum InsertError {
     #[error("{0}")]
-    TypeError(TypeError),
+    TypeError(TypeErrors),
     #[error("table not found: {0}")]
     TableNotFound(TableName),
+    #[error("key {key:} already exists in table {table:}")]
+    DuplicateKey { table: TableName, key: i32 },
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum DeleteError {
+    #[error("{0}")]
+    TypeError(TypeErrors),
+    #[error("table not found: {0}")]
+    TableNotFound(TableName),
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum UpdateError {
+    #[error("{0}")]
+    TypeError(TypeErrors),
+    #[error("table not found: {0}")]
+    TableNotFound(TableName),
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum AlterError {
+    #[error("{0}")]
+    TypeError(TypeErrors),
+    #[error("table not found: {0}")]
+    TableNotFound(TableName),
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum CatalogError {
+    #[error("{0}")]
+    TypeError(TypeErrors),
+    #[error("table not found: {0}")]
+    TableNotFound(TableName),
+    #[error(
+        "table {table_name:} already has rows, so cannot be changed from `{existing:}` to `{new:}`"
+    )]
+    IncompatibleRedefinition {
+        table_name: TableName,
+        existing: String,
+        new: String,
+    },
+}
+
+/// why a statement could not be parsed, and where
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("{message:}")]
+pub struct ParseError {
+    pub span: Span,
+    pub message: String,
+}
+
+/// which part of a statement a type error is about
+#[derive(Clone, Debug, PartialEq)]
+pub enum Location {
+    /// the table the statement uses
+    Table,
+    /// the key of an inserted row
+    Key,
+    /// the constructor of an inserted row, or one being added to a table
+    Constructor,
+    /// the inserted values as a whole
+    Values,
+    /// a selected, inserted, updated or declared column
+    Column(ColumnName),
+    /// the comparison on a column in the `where` clause
+    Where(ColumnName),
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Table => write!(f, "table"),
+            Location::Key => write!(f, "key"),
+            Location::Constructor => write!(f, "constructor"),
+            Location::Values => write!(f, "values"),
+            Location::Column(column_name) => write!(f, "column {column_name}"),
+            Location::Where(column_name) => write!(f, "where clause on column {column_name}"),
+        }
+    }
+}
+
+/// every type error found in a statement, and where each one is
+#[derive(Debug, PartialEq)]
+pub struct TypeErrors(pub Vec<(Location, TypeError)>);
+
+impl Display for TypeErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (location, error)) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{location}: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TypeErrors {}
+
+/// a range of bytes in the source of a statement
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// where each part of a parsed statement came from, so errors about
+/// a `Location` can point at the text that caused them
+#[derive(Debug, Default, PartialEq)]
+pub struct SourceMap(pub Vec<(Location, Span)>);
+
+impl SourceMap {
+    /// the first place the location appears, as a column can be named more than once
+    pub fn span(&self, location: &Location) -> Option<Span> {
+        self.0
+            .iter()
+            .find(|(found, _)| found == location)
+            .map(|(_, span)| *span)
+    }
 }
 
 #[derive(Debug, thiserror::Error, PartialEq)]
@@ -129,9 +337,7 @@ pub enum TypeError {
         table_name: TableName,
         column_name: ColumnName,
     },
-    #[error(
-        "type mismatch in column {column_name:} in table {table_name:}: {left:?} vs {right:?}"
-    )]
+    #[error("type mismatch in column {column_name:} in table {table_name:}: {left:} vs {right:}")]
     ColumnMismatch {
         table_name: TableName,
         column_name: ColumnName,
@@ -143,17 +349,71 @@ pub enum TypeError {
         table_name: TableName,
         column_name: ColumnName,
     },
-    #[error("expected type {expected_type:?} but found value {input_value:?}")]
+    #[error("expected type {expected_type:} but found value {input_value:}")]
     TypeMismatchInInput {
         expected_type: Type,
         input_value: ScalarValue,
     },
-    #[error("unknown scalar type for value {value:?}")]
+    #[error(
+        "unexpected columns {} when inserting into table {table_name:}",
+        comma_separated(.column_names)
+    )]
+    UnexpectedColumnsInInput {
+        table_name: TableName,
+        column_names: Vec<ColumnName>,
+    },
+    #[error("constructor {constructor:} not found in table {table:}")]
+    ConstructorNotFound {
+        table: TableName,
+        constructor: Constructor,
+    },
+    #[error("unknown scalar type for value {value:}")]
     UnknownScalarTypeForValue { value: ScalarValue },
     #[error("constructor not specified when inserting into table {table:}")]
     ConstructorNotSpecified { table: TableName },
     #[error("constructor specified when inserting into table {table:} but it is not required")]
     ConstructorSpecifiedButNotRequired { table: TableName },
+    #[error("key not specified when inserting into table {table:}")]
+    KeyNotSpecified { table: TableName },
+    #[error("key specified when inserting into table {table:} but it is generated automatically")]
+    KeySpecifiedButGenerated { table: TableName },
+    #[error("column {column_name:} already exists in table {table_name:}")]
+    ColumnAlreadyExists {
+        table_name: TableName,
+        column_name: ColumnName,
+    },
+    #[error(
+        "column {column_name:} is the last column in table {table_name:} so cannot be dropped"
+    )]
+    DropOfLastColumn {
+        table_name: TableName,
+        column_name: ColumnName,
+    },
+    #[error("constructor {constructor:} already exists in table {table:}")]
+    ConstructorAlreadyExists {
+        table: TableName,
+        constructor: Constructor,
+    },
+    #[error("table {table:} has a single constructor so constructors cannot be added to it")]
+    ConstructorAddedToSingleConstructorTable { table: TableName },
+    #[error("column {column_name:} is not in every constructor of table {table_name:} so cannot be updated")]
+    UpdateOfPartialColumn {
+        table_name: TableName,
+        column_name: ColumnName,
+    },
+    #[error("table {table:} has {count:} constructors but can have at most {MAX_CONSTRUCTORS}")]
+    TooManyConstructors { table: TableName, count: usize },
+}
+
+/// rows store which constructor they were made with in a single byte
+pub const MAX_CONSTRUCTORS: usize = 256;
+
+fn comma_separated<T: Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -163,6 +423,16 @@ pub enum ScalarType {
     Int,
 }
 
+impl Display for ScalarType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScalarType::String => write!(f, "String"),
+            ScalarType::Bool => write!(f, "Bool"),
+            ScalarType::Int => write!(f, "Int"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ScalarValue {
     Null,
@@ -171,20 +441,212 @@ pub enum ScalarValue {
     Int(i32),
 }
 
+impl Display for ScalarValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScalarValue::Null => write!(f, "null"),
+            ScalarValue::String(s) => {
+                write!(f, "\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            ScalarValue::Bool(b) => write!(f, "{b}"),
+            ScalarValue::Int(i) => write!(f, "{i}"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Type {
     Optional(Box<Type>),
     ScalarType(ScalarType),
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+impl Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Optional(inner) => write!(f, "optional {inner}"),
+            Type::ScalarType(scalar_type) => write!(f, "{scalar_type}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Table {
     pub name: TableName,
     pub columns: Columns,
+    /// keys are allocated by the engine from a per-table sequence
+    #[serde(default)]
+    pub auto_increment: bool,
+    /// the value used for a column when an insert leaves it out
+    #[serde(default)]
+    pub defaults: Defaults,
+}
+
+/// defaults belong to the constructor they were declared in, so
+/// `Cat { age: Int = 0 }` says nothing about the age of a `Dog`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Defaults {
+    /// for a table with a single constructor
+    pub columns: BTreeMap<ColumnName, ScalarValue>,
+    /// for each constructor that has any, in a table with several
+    pub constructors: BTreeMap<Constructor, BTreeMap<ColumnName, ScalarValue>>,
+}
+
+impl Defaults {
+    /// the defaults for rows stored with `constructor`, which is `None` for a
+    /// table with a single constructor
+    pub fn get(
+        &self,
+        constructor: Option<&Constructor>,
+    ) -> Option<&BTreeMap<ColumnName, ScalarValue>> {
+        match constructor {
+            None => Some(&self.columns),
+            Some(constructor) => self.constructors.get(constructor),
+        }
+    }
+
+    pub fn insert(
+        &mut self,
+        constructor: Option<&Constructor>,
+        column_name: ColumnName,
+        default: ScalarValue,
+    ) {
+        let defaults = match constructor {
+            None => &mut self.columns,
+            Some(constructor) => self.constructors.entry(constructor.clone()).or_default(),
+        };
+        defaults.insert(column_name, default);
+    }
+
+    /// forget the default for a column in every constructor
+    pub fn remove(&mut self, column_name: &ColumnName) {
+        self.columns.remove(column_name);
+        for defaults in self.constructors.values_mut() {
+            defaults.remove(column_name);
+        }
+        self.constructors.retain(|_, defaults| !defaults.is_empty());
+    }
+
+    /// move the default for a column to its new name in every constructor
+    pub fn rename(&mut self, from: &ColumnName, to: &ColumnName) {
+        for defaults in std::iter::once(&mut self.columns).chain(self.constructors.values_mut()) {
+            if let Some(default) = defaults.remove(from) {
+                defaults.insert(to.clone(), default);
+            }
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Columns {
     SingleConstructor(BTreeMap<ColumnName, ScalarType>),
     MultipleConstructors(BTreeMap<Constructor, BTreeMap<ColumnName, ScalarType>>),
 }
+
+/// prints the table in the same syntax used to declare it
+impl Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "type ")?;
+        write_name(f, &self.name.0, char::is_ascii_uppercase)?;
+        if self.auto_increment {
+            write!(f, " autoincrement")?;
+        }
+        write!(f, " ")?;
+        match &self.columns {
+            Columns::SingleConstructor(columns) => {
+                write_columns(f, columns, &self.defaults.columns)
+            }
+            Columns::MultipleConstructors(constructors) => {
+                write!(f, "{{ ")?;
+                for (index, (constructor, columns)) in constructors.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_name(f, &constructor.0, char::is_ascii_uppercase)?;
+                    write!(f, " ")?;
+                    let defaults = self.defaults.get(Some(constructor));
+                    write_columns(f, columns, defaults.unwrap_or(&BTreeMap::new()))?;
+                }
+                write!(f, " }}")
+            }
+        }
+    }
+}
+
+/// every word the parser treats as a keyword, in any case
+pub(crate) const KEYWORDS: &[&str] = &[
+    "add",
+    "alter",
+    "autoincrement",
+    "begin",
+    "column",
+    "commit",
+    "conflict",
+    "constructor",
+    "default",
+    "delete",
+    "describe",
+    "drop",
+    "false",
+    "from",
+    "insert",
+    "into",
+    "key",
+    "on",
+    "rename",
+    "replace",
+    "returning",
+    "rollback",
+    "select",
+    "set",
+    "show",
+    "tables",
+    "to",
+    "true",
+    "type",
+    "update",
+    "where",
+];
+
+// names that would not parse as they are need quotes, as column names start
+// with a lowercase letter and table and constructor names with an uppercase one.
+// a name that is also a keyword, like a column called `true` or a constructor
+// called `Key`, could be read as the keyword instead
+pub(crate) fn write_name(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    first: fn(&char) -> bool,
+) -> fmt::Result {
+    let mut chars = name.chars();
+    let plain = chars.next().as_ref().is_some_and(first)
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS
+            .iter()
+            .any(|keyword| name.eq_ignore_ascii_case(keyword));
+
+    if plain {
+        write!(f, "{name}")
+    } else if name.contains('`') {
+        write!(f, "\"{name}\"")
+    } else {
+        write!(f, "`{name}`")
+    }
+}
+
+pub(crate) fn write_columns(
+    f: &mut fmt::Formatter<'_>,
+    columns: &BTreeMap<ColumnName, ScalarType>,
+    defaults: &BTreeMap<ColumnName, ScalarValue>,
+) -> fmt::Result {
+    write!(f, "{{ ")?;
+    for (index, (column_name, scalar_type)) in columns.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write_name(f, &column_name.0, char::is_ascii_lowercase)?;
+        write!(f, ": {scalar_type}")?;
+        if let Some(default) = defaults.get(column_name) {
+            write!(f, " = {default}")?;
+        }
+    }
+    write!(f, " }}")
+}
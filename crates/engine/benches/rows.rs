@@ -0,0 +1,142 @@
+//! compares the binary row format with the JSON rows we used to store
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use engine::encoding::{decode_row, encode_row, Row};
+use engine_core::{ColumnName, Constructor, Insert, InsertValue, OnConflict, ScalarValue, Table};
+use rocksdb::{Options, DB};
+use std::collections::BTreeMap;
+
+const ROWS: i32 = 10_000;
+
+fn pet_table() -> Table {
+    let (_, table) = engine_core::parse_table(
+        "type Pet { Cat { age: Int, name: String }, Dog { age: Int, name: String, likes_stick: Bool } }",
+    )
+    .expect("parse_table");
+    table
+}
+
+fn dog(age: i32) -> Row {
+    let mut values = BTreeMap::new();
+    values.insert(ColumnName("age".to_string()), ScalarValue::Int(age));
+    values.insert(
+        ColumnName("name".to_string()),
+        ScalarValue::String("Mr Dog".to_string()),
+    );
+    values.insert(
+        ColumnName("likes_stick".to_string()),
+        ScalarValue::Bool(true),
+    );
+    Row {
+        constructor: Some(Constructor("Dog".to_string())),
+        values,
+    }
+}
+
+/// the row as it would have been stored before the binary format
+fn legacy_json(row: &Row) -> Vec<u8> {
+    let mut object = serde_json::Map::new();
+    for (column, value) in &row.values {
+        let json = match value {
+            ScalarValue::Int(i) => serde_json::Value::from(*i),
+            ScalarValue::Bool(b) => serde_json::Value::from(*b),
+            ScalarValue::String(s) => serde_json::Value::from(s.clone()),
+            ScalarValue::Null => serde_json::Value::Null,
+        };
+        object.insert(column.to_string(), json);
+    }
+    if let Some(constructor) = &row.constructor {
+        object.insert("_type".to_string(), constructor.to_string().into());
+    }
+    serde_json::to_vec(&object).unwrap()
+}
+
+fn row_size(c: &mut Criterion) {
+    let table = pet_table();
+    let binary = encode_row(&table, &dog(21)).unwrap();
+    let json = legacy_json(&dog(21));
+
+    // the point of the binary encoding is that rows take less space
+    assert!(binary.len() < json.len());
+
+    let mut group = c.benchmark_group("decode row");
+    group.bench_function("json", |b| {
+        b.iter(|| decode_row(&table, black_box(&json)).unwrap());
+    });
+    group.bench_function("binary", |b| {
+        b.iter(|| decode_row(&table, black_box(&binary)).unwrap());
+    });
+    group.finish();
+}
+
+/// fill a database with `ROWS` dogs, stored by `write_row`
+fn with_pets(write_row: impl Fn(&DB, i32), f: impl FnOnce(&DB)) {
+    let path = format!("./bench_storage{}", rand::random::<i32>());
+    {
+        let db = DB::open_default(path.clone()).unwrap();
+        engine::data::insert_table(&db, &pet_table()).unwrap();
+        for key in 1..=ROWS {
+            write_row(&db, key);
+        }
+        f(&db);
+    }
+    let _ = DB::destroy(&Options::default(), path);
+}
+
+fn scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("select age, name from Pet");
+
+    with_pets(
+        |db, key| {
+            db.put(format!("data_Pet_{key}"), legacy_json(&dog(key)))
+                .unwrap();
+        },
+        |db| {
+            group.bench_function("json", |b| {
+                b.iter_batched(
+                    || {
+                        engine_core::parse_select("select age, name from Pet")
+                            .unwrap()
+                            .1
+                    },
+                    |select| engine::select::select(db, select).unwrap(),
+                    BatchSize::SmallInput,
+                );
+            });
+        },
+    );
+
+    with_pets(
+        |db, key| {
+            let Row { values, .. } = dog(key);
+            let insert = Insert {
+                table: pet_table().name,
+                key: Some(key),
+                value: InsertValue::Multiple {
+                    constructor: Constructor("Dog".to_string()),
+                    values,
+                },
+                on_conflict: OnConflict::Fail,
+                returning_key: false,
+            };
+            engine::insert::insert(db, &insert).unwrap();
+        },
+        |db| {
+            group.bench_function("binary", |b| {
+                b.iter_batched(
+                    || {
+                        engine_core::parse_select("select age, name from Pet")
+                            .unwrap()
+                            .1
+                    },
+                    |select| engine::select::select(db, select).unwrap(),
+                    BatchSize::SmallInput,
+                );
+            });
+        },
+    );
+
+    group.finish();
+}
+
+criterion_group!(benches, row_size, scan);
+criterion_main!(benches);
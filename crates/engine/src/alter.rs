@@ -0,0 +1,160 @@
+//! changing a table definition, rewriting its rows to match
+use crate::data::{lookup_table_for_write, read_row, table_row_keys, write_table};
+use crate::encoding::{encode_row, Row};
+use crate::storage::Storage;
+use crate::transaction::{autocommit, Transaction};
+use crate::EngineError;
+use engine_core::{AlterError, AlterTable, TableChange};
+use std::collections::BTreeMap;
+
+/// typecheck and apply a change to a table, returning how many rows were rewritten
+pub fn alter_table(db: &impl Storage, alter: &AlterTable) -> Result<usize, EngineError> {
+    autocommit(db, |tx| alter_table_in(tx, alter))
+}
+
+pub(crate) fn alter_table_in(
+    tx: &mut Transaction<impl Storage>,
+    alter: &AlterTable,
+) -> Result<usize, EngineError> {
+    let table = match lookup_table_for_write(tx, &alter.table)? {
+        Some(table) => Ok(table),
+        None => Err(AlterError::TableNotFound(alter.table.clone())),
+    }?;
+
+    let mut tables = BTreeMap::new();
+    tables.insert(alter.table.clone(), table);
+
+    let new_table = engine_core::typecheck_alter(&tables, alter).map_err(AlterError::TypeError)?;
+    let old_table = &tables[&alter.table];
+
+    // every row is read with the old definition and written with the new one, so
+    // they all change in the same commit as the schema. Watching the table makes a
+    // row written by anyone else in the meantime a conflict rather than a row left
+    // in the old encoding
+    tx.watch_table(&alter.table)?;
+    let keys = table_row_keys(tx, &alter.table).collect::<Result<Vec<_>, _>>()?;
+    for key in &keys {
+        let Some(value) = tx.get(key)? else {
+            continue;
+        };
+        let mut row = read_row(old_table, key, &value)?;
+        migrate_row(&alter.change, &mut row);
+        let value = encode_row(&new_table, &row).map_err(|reason| EngineError::CorruptRow {
+            key: String::from_utf8_lossy(key).to_string(),
+            reason,
+        })?;
+        tx.put(key, value)?;
+    }
+
+    write_table(tx, &new_table)?;
+    Ok(keys.len())
+}
+
+fn migrate_row(change: &TableChange, row: &mut Row) {
+    match change {
+        TableChange::AddColumn {
+            column, default, ..
+        } => {
+            row.values.insert(column.clone(), default.clone());
+        }
+        TableChange::DropColumn { column } => {
+            row.values.remove(column);
+        }
+        TableChange::RenameColumn { from, to } => {
+            if let Some(value) = row.values.remove(from) {
+                row.values.insert(to.clone(), value);
+            }
+        }
+        // the values stay the same, but the constructor may be stored differently
+        TableChange::AddConstructor { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::alter_table;
+    use crate::data::insert_table;
+    use crate::storage::MemoryStorage;
+    use crate::EngineError;
+    use engine_core::{AlterError, ColumnName, Location, TableName, TypeError, TypeErrors};
+
+    fn select_json(db: &MemoryStorage, select_sql: &str) -> Vec<serde_json::Value> {
+        let (_, select) = engine_core::parse_select(select_sql).expect("parse_select");
+        crate::select::select(db, select).expect("select").to_json()
+    }
+
+    #[test]
+    fn test_alter_columns() {
+        let db = MemoryStorage::default();
+        let (_, table) = engine_core::parse_table("type User { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+        let (_, insert) =
+            engine_core::parse_insert("insert into User key 1 { age: 27 }").expect("parse_insert");
+        crate::insert::insert(&db, &insert).expect("insert");
+
+        for alter_sql in [
+            "alter type User add column email: String default \"\"",
+            "alter type User rename column age to years",
+        ] {
+            let (_, alter) = engine_core::parse_alter(alter_sql).expect("parse_alter");
+            assert_eq!(alter_table(&db, &alter), Ok(1));
+        }
+
+        assert_eq!(
+            select_json(&db, "select email, years from User"),
+            vec![
+                serde_json::from_str::<serde_json::Value>("{\"email\":\"\",\"years\":27}").unwrap()
+            ]
+        );
+
+        // later rows can leave out the new column
+        let (_, insert) = engine_core::parse_insert("insert into User key 2 { years: 100 }")
+            .expect("parse_insert");
+        crate::insert::insert(&db, &insert).expect("insert");
+
+        let (_, alter) =
+            engine_core::parse_alter("alter type User drop column email").expect("parse_alter");
+        assert_eq!(alter_table(&db, &alter), Ok(2));
+
+        let (_, insert) = engine_core::parse_insert("insert into User key 3 { years: 100 }")
+            .expect("parse_insert");
+        crate::insert::insert(&db, &insert).expect("insert");
+
+        assert_eq!(select_json(&db, "select years from User").len(), 3);
+
+        let (_, alter) =
+            engine_core::parse_alter("alter type User drop column age").expect("parse_alter");
+        assert_eq!(
+            alter_table(&db, &alter),
+            Err(EngineError::Alter(AlterError::TypeError(TypeErrors(vec![
+                (
+                    Location::Column(ColumnName("age".to_string())),
+                    TypeError::ColumnNotFound {
+                        table_name: TableName("User".to_string()),
+                        column_name: ColumnName("age".to_string())
+                    }
+                )
+            ]))))
+        );
+    }
+
+    #[test]
+    fn test_add_constructor() {
+        let db = MemoryStorage::default();
+        let (_, table) =
+            engine_core::parse_table("type Pet { Dog { age: Int }, Horse { age: Int } }")
+                .expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+        let (_, insert) = engine_core::parse_insert("insert into Pet key 1 Horse { age: 27 }")
+            .expect("parse_insert");
+        crate::insert::insert(&db, &insert).expect("insert");
+
+        // `Cat` comes before `Horse`, so the stored constructor of every row changes
+        let (_, alter) =
+            engine_core::parse_alter("alter type Pet add constructor Cat { age: Int }")
+                .expect("parse_alter");
+        assert_eq!(alter_table(&db, &alter), Ok(1));
+
+        assert_eq!(select_json(&db, "select Horse { age } from Pet").len(), 1);
+    }
+}
@@ -0,0 +1,238 @@
+//! finding out which tables exist, and getting rid of them
+use crate::data::{
+    lookup_table, lookup_table_in, sequence_key, table_key, table_row_keys, TABLE_PREFIX,
+};
+use crate::storage::Storage;
+use crate::transaction::{autocommit, Transaction};
+use crate::EngineError;
+use engine_core::{CatalogError, Table, TableName};
+
+/// the name of every table, in order
+pub fn list_tables(db: &impl Storage) -> Result<Vec<TableName>, EngineError> {
+    table_names(db.scan(TABLE_PREFIX))
+}
+
+pub(crate) fn list_tables_in(
+    tx: &Transaction<impl Storage>,
+) -> Result<Vec<TableName>, EngineError> {
+    table_names(tx.scan(TABLE_PREFIX))
+}
+
+fn table_names(
+    rows: impl Iterator<Item = Result<(Vec<u8>, Vec<u8>), EngineError>>,
+) -> Result<Vec<TableName>, EngineError> {
+    rows.map(|item| {
+        let (key, _) = item?;
+        let name = String::from_utf8_lossy(&key[TABLE_PREFIX.len()..]).to_string();
+        Ok(TableName(name))
+    })
+    .collect()
+}
+
+/// the definition of a table, which prints in the same syntax used to declare it
+pub fn describe_table(db: &impl Storage, table_name: &TableName) -> Result<Table, EngineError> {
+    found(table_name, lookup_table(db, table_name)?)
+}
+
+pub(crate) fn describe_table_in(
+    tx: &Transaction<impl Storage>,
+    table_name: &TableName,
+) -> Result<Table, EngineError> {
+    found(table_name, lookup_table_in(tx, table_name)?)
+}
+
+fn found(table_name: &TableName, table: Option<Table>) -> Result<Table, EngineError> {
+    table.ok_or_else(|| CatalogError::TableNotFound(table_name.clone()).into())
+}
+
+/// remove a table, all of its rows and its key sequence in one go,
+/// returning how many rows went with it
+pub fn drop_table(db: &impl Storage, table_name: &TableName) -> Result<usize, EngineError> {
+    autocommit(db, |tx| drop_table_in(tx, table_name))
+}
+
+pub(crate) fn drop_table_in(
+    tx: &mut Transaction<impl Storage>,
+    table_name: &TableName,
+) -> Result<usize, EngineError> {
+    let key = table_key(table_name);
+    if tx.get(key.as_bytes())?.is_none() {
+        return Err(CatalogError::TableNotFound(table_name.clone()).into());
+    }
+
+    // a row written by anyone else before this commits would be left behind
+    tx.watch_table(table_name)?;
+    let rows = table_row_keys(tx, table_name).collect::<Result<Vec<_>, _>>()?;
+    for row_key in &rows {
+        tx.delete_key(row_key)?;
+    }
+    tx.delete_key(sequence_key(table_name).as_bytes())?;
+    tx.delete_key(key.as_bytes())?;
+
+    Ok(rows.len())
+}
+
+#[cfg(test)]
+mod testing {
+    use super::{describe_table, drop_table, list_tables};
+    use crate::data::insert_table;
+    use crate::storage::{MemoryStorage, Storage};
+    use crate::EngineError;
+    use engine_core::{CatalogError, ScalarValue, SelectError, TableName};
+
+    fn setup(db: &MemoryStorage) {
+        for table_sql in [
+            "type User autoincrement { age: Int }",
+            "type User_Pet { name: String }",
+        ] {
+            let (_, table) = engine_core::parse_table(table_sql).expect("parse_table");
+            insert_table(db, &table).expect("insert_table");
+        }
+        for insert_sql in [
+            "insert into User { age: 27 }",
+            "insert into User { age: 100 }",
+            "insert into User_Pet key 1 { name: \"Cat\" }",
+        ] {
+            let (_, insert) = engine_core::parse_insert(insert_sql).expect("parse_insert");
+            crate::insert::insert(db, &insert).expect("insert");
+        }
+    }
+
+    #[test]
+    fn test_list_and_describe() {
+        let db = MemoryStorage::default();
+        setup(&db);
+
+        assert_eq!(
+            list_tables(&db),
+            Ok(vec![
+                TableName("User".to_string()),
+                TableName("User_Pet".to_string())
+            ])
+        );
+
+        assert_eq!(
+            describe_table(&db, &TableName("User".to_string())).map(|table| table.to_string()),
+            Ok("type User autoincrement { age: Int }".to_string())
+        );
+        assert_eq!(
+            describe_table(&db, &TableName("Missing".to_string())),
+            Err(EngineError::Catalog(CatalogError::TableNotFound(
+                TableName("Missing".to_string())
+            )))
+        );
+    }
+
+    #[test]
+    fn test_drop_table() {
+        let db = MemoryStorage::default();
+        setup(&db);
+
+        assert_eq!(drop_table(&db, &TableName("User".to_string())), Ok(2));
+
+        assert_eq!(
+            list_tables(&db),
+            Ok(vec![TableName("User_Pet".to_string())])
+        );
+        assert_eq!(db.get(b"sequence_User"), Ok(None));
+        assert_eq!(db.scan("data_User_").count(), 1);
+
+        let (_, select_sql) =
+            engine_core::parse_select("select age from User").expect("parse_select");
+        assert_eq!(
+            crate::select::select(&db, select_sql),
+            Err(EngineError::Select(SelectError::TableNotFound(TableName(
+                "User".to_string()
+            ))))
+        );
+
+        assert_eq!(
+            drop_table(&db, &TableName("User".to_string())),
+            Err(EngineError::Catalog(CatalogError::TableNotFound(
+                TableName("User".to_string())
+            )))
+        );
+    }
+
+    #[test]
+    fn test_tables_sharing_a_prefix() {
+        let db = MemoryStorage::default();
+        setup(&db);
+
+        // rows from `User_Pet` are stored under `data_User_Pet_`, which `User`
+        // must not pick up
+        let select_ages = |db: &MemoryStorage| {
+            let (_, select_sql) =
+                engine_core::parse_select("select age from User").expect("parse_select");
+            crate::select::select(db, select_sql).map(|result| result.rows)
+        };
+        assert_eq!(
+            select_ages(&db),
+            Ok(vec![
+                vec![ScalarValue::Int(27)],
+                vec![ScalarValue::Int(100)]
+            ])
+        );
+
+        let (_, update_sql) =
+            engine_core::parse_update("update User set age = 4").expect("parse_update");
+        assert_eq!(crate::update::update(&db, &update_sql), Ok(2));
+        assert_eq!(
+            select_ages(&db),
+            Ok(vec![vec![ScalarValue::Int(4)], vec![ScalarValue::Int(4)]])
+        );
+
+        let (_, delete_sql) = engine_core::parse_delete("delete from User").expect("parse_delete");
+        assert_eq!(crate::delete::delete(&db, &delete_sql), Ok(2));
+        assert_eq!(select_ages(&db), Ok(vec![]));
+
+        let (_, select_sql) =
+            engine_core::parse_select("select name from User_Pet").expect("parse_select");
+        assert_eq!(
+            crate::select::select(&db, select_sql).map(|result| result.rows),
+            Ok(vec![vec![ScalarValue::String("Cat".to_string())]])
+        );
+    }
+
+    #[test]
+    fn test_redefine_table() {
+        let db = MemoryStorage::default();
+        setup(&db);
+
+        // declaring the same thing again is fine
+        let (_, same) =
+            engine_core::parse_table("type User autoincrement { age: Int }").expect("parse_table");
+        assert_eq!(insert_table(&db, &same), Ok(()));
+
+        // but changing a table with rows in it is not
+        let (_, changed) = engine_core::parse_table("type User autoincrement { age: String }")
+            .expect("parse_table");
+        assert_eq!(
+            insert_table(&db, &changed),
+            Err(EngineError::Catalog(
+                CatalogError::IncompatibleRedefinition {
+                    table_name: TableName("User".to_string()),
+                    existing: "type User autoincrement { age: Int }".to_string(),
+                    new: "type User autoincrement { age: String }".to_string(),
+                }
+            ))
+        );
+        assert_eq!(
+            describe_table(&db, &TableName("User".to_string())),
+            Ok(same)
+        );
+
+        // an empty table can be changed
+        drop_table(&db, &TableName("User".to_string())).expect("drop_table");
+        let (_, empty) =
+            engine_core::parse_table("type User_Thing { age: Int }").expect("parse_table");
+        insert_table(&db, &empty).expect("insert_table");
+        let (_, changed_empty) =
+            engine_core::parse_table("type User_Thing { name: String }").expect("parse_table");
+        assert_eq!(insert_table(&db, &changed_empty), Ok(()));
+        assert_eq!(
+            describe_table(&db, &TableName("User_Thing".to_string())),
+            Ok(changed_empty)
+        );
+    }
+}
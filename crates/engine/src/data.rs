@@ -1,54 +1,244 @@
-//! functions for smashing stuff into `RocksDB`
-use super::helpers::to_serde_json;
-use engine_core::{ColumnName, Insert, InsertValue, ScalarValue, Table, TableName};
-use rocksdb::DB;
-use serde_json::Value;
-use std::collections::BTreeMap;
-
-fn to_serde_json_object(
-    items: &BTreeMap<ColumnName, ScalarValue>,
-) -> BTreeMap<ColumnName, serde_json::Value> {
-    let mut result = BTreeMap::new();
-    for (key, item) in items {
-        result.insert(key.clone(), to_serde_json(item));
+//! functions for smashing stuff into storage
+use super::encoding::{decode_row, encode_row, Row};
+use super::helpers::{apply_expression, is_true};
+use super::storage::Storage;
+use crate::transaction::{autocommit, Transaction};
+use crate::EngineError;
+use engine_core::{CatalogError, Expression, Insert, InsertError, OnConflict, Table, TableName};
+
+pub(crate) fn sequence_key(table_name: &TableName) -> String {
+    format!("sequence_{table_name}")
+}
+
+/// the next key for an `autoincrement` table, starting at 1
+fn next_sequence_value(
+    tx: &mut Transaction<impl Storage>,
+    table_name: &TableName,
+) -> Result<i32, EngineError> {
+    let key = sequence_key(table_name);
+    let Some(raw) = tx.get(key.as_bytes())? else {
+        return Ok(1);
+    };
+    let current = std::str::from_utf8(&raw)
+        .ok()
+        .and_then(|current| current.parse::<i32>().ok())
+        .ok_or_else(|| EngineError::CorruptRow {
+            key,
+            reason: "sequence is not an integer".to_string(),
+        })?;
+    current
+        .checked_add(1)
+        .ok_or_else(|| EngineError::SequenceExhausted(table_name.clone()))
+}
+
+/// insert a row, returning the key it was stored under
+pub fn insert(
+    tx: &mut Transaction<impl Storage>,
+    table: &Table,
+    insert: &Insert,
+) -> Result<i32, EngineError> {
+    let row_id = if let Some(key) = insert.key {
+        key
+    } else {
+        // the new counter value is committed along with the row
+        let key = next_sequence_value(tx, &insert.table)?;
+        tx.put(
+            sequence_key(&insert.table).as_bytes(),
+            key.to_string().into_bytes(),
+        )?;
+        key
+    };
+
+    let key = row_key(&insert.table, row_id);
+
+    // unless we've been told to overwrite, an existing row is an error
+    if insert.on_conflict == OnConflict::Fail && tx.get(key.as_bytes())?.is_some() {
+        return Err(EngineError::Insert(InsertError::DuplicateKey {
+            table: insert.table.clone(),
+            key: row_id,
+        }));
     }
-    result
+
+    let mut row = Row::from_insert_value(&insert.value);
+    row.fill_defaults(table);
+
+    let value = encode_row(table, &row).map_err(|reason| EngineError::CorruptRow {
+        key: key.clone(),
+        reason,
+    })?;
+    tx.put(key.as_bytes(), value)?;
+    Ok(row_id)
+}
+
+fn row_key(table_name: &TableName, row_id: i32) -> String {
+    format!("{}{row_id}", row_prefix(table_name))
+}
+
+fn row_prefix(table_name: &TableName) -> String {
+    format!("data_{table_name}_")
+}
+
+/// the key and contents of every row in the table
+pub(crate) fn scan_table<'a>(
+    db: &'a impl Storage,
+    table_name: &TableName,
+) -> impl Iterator<Item = Result<(Vec<u8>, Vec<u8>), EngineError>> + 'a {
+    only_table_rows(table_name, db.scan(&row_prefix(table_name)))
 }
 
-/// turn an `InsertValue` into `RocksDB` row
-fn insert_value_to_json(insert_value: &InsertValue) -> Value {
-    match insert_value {
-        InsertValue::Single { values } => {
-            serde_json::to_value(to_serde_json_object(values)).unwrap()
+/// like `scan_table`, including the transaction's own uncommitted writes
+pub(crate) fn scan_table_in<'a>(
+    tx: &'a Transaction<impl Storage>,
+    table_name: &TableName,
+) -> impl Iterator<Item = Result<(Vec<u8>, Vec<u8>), EngineError>> + 'a {
+    only_table_rows(table_name, tx.scan(&row_prefix(table_name)))
+}
+
+/// `data_User_` is also the start of `data_User_Pet_1`, so only keys followed by
+/// a row id belong to the table
+fn only_table_rows<'a>(
+    table_name: &TableName,
+    rows: impl Iterator<Item = Result<(Vec<u8>, Vec<u8>), EngineError>> + 'a,
+) -> impl Iterator<Item = Result<(Vec<u8>, Vec<u8>), EngineError>> + 'a {
+    let prefix_len = row_prefix(table_name).len();
+    rows.filter(move |item| {
+        item.as_ref().map_or(true, |(key, _)| {
+            std::str::from_utf8(&key[prefix_len..])
+                .is_ok_and(|row_id| row_id.parse::<i32>().is_ok())
+        })
+    })
+}
+
+/// the key of every row in the table
+pub(crate) fn table_row_keys<'a>(
+    tx: &'a Transaction<impl Storage>,
+    table_name: &TableName,
+) -> impl Iterator<Item = Result<Vec<u8>, EngineError>> + 'a {
+    scan_table_in(tx, table_name).map(|item| item.map(|(key, _)| key))
+}
+
+/// decode a stored row, complaining if it doesn't match the table
+pub(crate) fn read_row(table: &Table, key: &[u8], value: &[u8]) -> Result<Row, EngineError> {
+    decode_row(table, value).map_err(|reason| EngineError::CorruptRow {
+        key: String::from_utf8_lossy(key).to_string(),
+        reason,
+    })
+}
+
+/// the key and contents of every row in the table that `expression` is true for.
+/// the whole table is watched, so commit fails if another writer changes a row
+/// we looked at, or adds one that might have matched
+pub(crate) fn matching_rows(
+    tx: &mut Transaction<impl Storage>,
+    table: &Table,
+    expression: &Expression,
+) -> Result<Vec<(Vec<u8>, Row)>, EngineError> {
+    tx.watch_table(&table.name)?;
+    let mut rows = vec![];
+    for item in scan_table_in(tx, &table.name) {
+        let (key, value) = item?;
+        let row = read_row(table, &key, &value)?;
+        if is_true(&apply_expression(&row.values, expression)) {
+            rows.push((key, row));
         }
-        InsertValue::Multiple {
-            constructor,
-            values,
-        } => {
-            let mut json = serde_json::to_value(to_serde_json_object(values)).unwrap();
-            let object = json.as_object_mut().unwrap();
-            object.insert("_type".to_string(), Value::String(constructor.0.clone()));
-            Value::Object(object.clone())
+    }
+    Ok(rows)
+}
+
+pub(crate) const TABLE_PREFIX: &str = "table_";
+
+pub(crate) fn table_key(table_name: &TableName) -> String {
+    format!("{TABLE_PREFIX}{table_name}")
+}
+
+/// store a table definition. declaring the same table again does nothing, and a
+/// table can only be changed while it has no rows
+pub fn insert_table(db: &impl Storage, table: &Table) -> Result<(), EngineError> {
+    autocommit(db, |tx| insert_table_in(tx, table))
+}
+
+pub(crate) fn insert_table_in(
+    tx: &mut Transaction<impl Storage>,
+    table: &Table,
+) -> Result<(), EngineError> {
+    engine_core::typecheck_table(table).map_err(CatalogError::TypeError)?;
+
+    let key = table_key(&table.name);
+
+    if let Some(raw) = tx.get(key.as_bytes())? {
+        let existing = parse_table_schema(&table.name, &raw)?;
+        if existing == *table {
+            return Ok(());
+        }
+        if table_row_keys(tx, &table.name)
+            .next()
+            .transpose()?
+            .is_some()
+        {
+            return Err(CatalogError::IncompatibleRedefinition {
+                table_name: table.name.clone(),
+                existing: existing.to_string(),
+                new: table.to_string(),
+            }
+            .into());
         }
     }
+
+    write_table(tx, table)
+}
+
+pub(crate) fn write_table(
+    tx: &mut Transaction<impl Storage>,
+    table: &Table,
+) -> Result<(), EngineError> {
+    tx.put(
+        table_key(&table.name).as_bytes(),
+        serde_json::to_string(&table).unwrap().into_bytes(),
+    )
+}
+
+/// like `lookup_table_in`, but the commit fails if another writer changes or
+/// drops the table first. anything that writes rows needs this, as the rows are
+/// encoded with the definition it found
+pub(crate) fn lookup_table_for_write(
+    tx: &mut Transaction<impl Storage>,
+    table_name: &TableName,
+) -> Result<Option<Table>, EngineError> {
+    let key = table_key(table_name);
+    let Some(raw) = tx.get(key.as_bytes())? else {
+        return Ok(None);
+    };
+    parse_table_schema(table_name, &raw).map(Some)
 }
 
-pub fn insert(db: &DB, insert: &Insert) -> i32 {
-    let key = format!("data_{}_{}", insert.table, insert.key);
-    let value = serde_json::to_string(&insert_value_to_json(&insert.value)).unwrap();
-    let _ = db.put(key, value);
-    1
+/// like `lookup_table`, but seeing tables declared or changed earlier in the transaction
+pub(crate) fn lookup_table_in(
+    tx: &Transaction<impl Storage>,
+    table_name: &TableName,
+) -> Result<Option<Table>, EngineError> {
+    let key = table_key(table_name);
+    let Some(raw) = tx.read(key.as_bytes())? else {
+        return Ok(None);
+    };
+    parse_table_schema(table_name, &raw).map(Some)
 }
 
-pub fn insert_table(db: &DB, table: &Table) -> i32 {
-    let key = format!("table_{}", table.name);
-    let _ = db.put(key, serde_json::to_string(&table).unwrap());
-    1
+pub fn lookup_table(
+    db: &impl Storage,
+    table_name: &TableName,
+) -> Result<Option<Table>, EngineError> {
+    let key = table_key(table_name);
+    let Some(raw) = db.get(key.as_bytes())? else {
+        return Ok(None);
+    };
+    parse_table_schema(table_name, &raw).map(Some)
 }
 
-pub fn lookup_table(db: &DB, table_name: &TableName) -> Option<Table> {
-    let key = format!("table_{table_name}");
-    let raw = db.get(key).unwrap()?;
-    let json = std::str::from_utf8(&raw).ok()?;
-    serde_json::from_str(json).ok()?
+fn parse_table_schema(table_name: &TableName, raw: &[u8]) -> Result<Table, EngineError> {
+    let corrupt_schema = |reason: String| EngineError::CorruptSchema {
+        table_name: table_name.clone(),
+        reason,
+    };
+    let json = std::str::from_utf8(raw).map_err(|e| corrupt_schema(e.to_string()))?;
+    serde_json::from_str(json).map_err(|e| corrupt_schema(e.to_string()))
 }
@@ -0,0 +1,32 @@
+use crate::data::{lookup_table_for_write, matching_rows};
+use crate::storage::Storage;
+use crate::transaction::{autocommit, Transaction};
+use crate::EngineError;
+use engine_core::{Delete, DeleteError};
+use std::collections::BTreeMap;
+
+/// typecheck and remove every matching row, returning how many went
+pub fn delete(db: &impl Storage, delete: &Delete) -> Result<usize, EngineError> {
+    autocommit(db, |tx| delete_in(tx, delete))
+}
+
+pub(crate) fn delete_in(
+    tx: &mut Transaction<impl Storage>,
+    delete: &Delete,
+) -> Result<usize, EngineError> {
+    let table = match lookup_table_for_write(tx, &delete.table)? {
+        Some(table) => Ok(table),
+        None => Err(DeleteError::TableNotFound(delete.table.clone())),
+    }?;
+
+    let mut tables = BTreeMap::new();
+    tables.insert(delete.table.clone(), table);
+
+    engine_core::typecheck_delete(&tables, delete).map_err(DeleteError::TypeError)?;
+
+    let rows = matching_rows(tx, &tables[&delete.table], &delete.r#where)?;
+    for (key, _) in &rows {
+        tx.delete_key(key)?;
+    }
+    Ok(rows.len())
+}
@@ -0,0 +1,288 @@
+//! the on-disk format for rows
+//!
+//! Rows are stored as a version byte, then the index of the constructor in the
+//! `Table` definition (always `0` for tables with a single constructor), then each
+//! column's value in the order the `Table` declares them. Column names and types
+//! come from the schema, so they aren't repeated in every row:
+//!
+//! * `Int` - 4 bytes, little endian
+//! * `Bool` - 1 byte, `0` or `1`
+//! * `String` - 4 byte little endian length, then that many bytes of UTF-8
+//!
+//! Rows written before this format existed are JSON objects, which we can still
+//! read.
+use engine_core::{ColumnName, Columns, Constructor, InsertValue, ScalarType, ScalarValue, Table};
+use std::collections::BTreeMap;
+
+pub const ROW_FORMAT_VERSION: u8 = 1;
+
+/// the first byte of the legacy JSON rows
+const LEGACY_JSON_ROW: u8 = b'{';
+
+/// a row read back from storage
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    /// `None` for tables with a single constructor
+    pub constructor: Option<Constructor>,
+    pub values: BTreeMap<ColumnName, ScalarValue>,
+}
+
+impl Row {
+    pub fn from_insert_value(insert_value: &InsertValue) -> Row {
+        match insert_value {
+            InsertValue::Single { values } => Row {
+                constructor: None,
+                values: values.clone(),
+            },
+            InsertValue::Multiple {
+                constructor,
+                values,
+            } => Row {
+                constructor: Some(constructor.clone()),
+                values: values.clone(),
+            },
+        }
+    }
+}
+
+impl Row {
+    /// fill in any columns the row leaves out that the table has a default for
+    pub fn fill_defaults(&mut self, table: &Table) {
+        let Ok((_, columns)) = row_columns(table, self.constructor.as_ref()) else {
+            return;
+        };
+        let Some(defaults) = table.defaults.get(self.constructor.as_ref()) else {
+            return;
+        };
+        for column_name in columns.keys() {
+            if let Some(default) = defaults.get(column_name) {
+                self.values
+                    .entry(column_name.clone())
+                    .or_insert_with(|| default.clone());
+            }
+        }
+    }
+}
+
+/// the constructor index and columns to use for a row
+fn row_columns<'a>(
+    table: &'a Table,
+    constructor: Option<&Constructor>,
+) -> Result<(u8, &'a BTreeMap<ColumnName, ScalarType>), String> {
+    match (&table.columns, constructor) {
+        (Columns::SingleConstructor(columns), None) => Ok((0, columns)),
+        (Columns::MultipleConstructors(constructors), Some(constructor)) => constructors
+            .iter()
+            .enumerate()
+            .find(|(_, (name, _))| *name == constructor)
+            // `typecheck_table` keeps tables to `MAX_CONSTRUCTORS`, so the index fits
+            .and_then(|(index, (_, columns))| Some((u8::try_from(index).ok()?, columns)))
+            .ok_or_else(|| format!("unknown constructor {constructor}")),
+        (Columns::SingleConstructor(_), Some(constructor)) => Err(format!(
+            "constructor {constructor} given for table with a single constructor"
+        )),
+        (Columns::MultipleConstructors(_), None) => {
+            Err("no constructor given for table with multiple constructors".to_string())
+        }
+    }
+}
+
+/// turn a row into bytes, using the order of columns in `table`.
+/// the row should already have been typechecked against the table
+pub fn encode_row(table: &Table, row: &Row) -> Result<Vec<u8>, String> {
+    let (constructor_index, columns) = row_columns(table, row.constructor.as_ref())?;
+
+    let mut bytes = vec![ROW_FORMAT_VERSION, constructor_index];
+
+    for (column_name, scalar_type) in columns {
+        match (scalar_type, row.values.get(column_name)) {
+            (ScalarType::Int, Some(ScalarValue::Int(i))) => bytes.extend(i.to_le_bytes()),
+            (ScalarType::Bool, Some(ScalarValue::Bool(b))) => bytes.push(u8::from(*b)),
+            (ScalarType::String, Some(ScalarValue::String(s))) => {
+                let len = u32::try_from(s.len())
+                    .map_err(|_| format!("value for column {column_name} is too long"))?;
+                bytes.extend(len.to_le_bytes());
+                bytes.extend(s.as_bytes());
+            }
+            (_, value) => {
+                return Err(format!(
+                    "expected {scalar_type:?} for column {column_name} but found {value:?}"
+                ))
+            }
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// read a row written by `encode_row` (or a legacy JSON row) back into values
+pub fn decode_row(table: &Table, bytes: &[u8]) -> Result<Row, String> {
+    match bytes.first() {
+        Some(&ROW_FORMAT_VERSION) => decode_binary_row(table, &bytes[1..]),
+        Some(&LEGACY_JSON_ROW) => decode_json_row(bytes),
+        Some(version) => Err(format!("unknown row format version {version}")),
+        None => Err("row is empty".to_string()),
+    }
+}
+
+fn decode_binary_row(table: &Table, bytes: &[u8]) -> Result<Row, String> {
+    let mut reader = Reader { bytes };
+
+    let constructor_index = reader.take(1)?[0];
+    let (constructor, columns) = match &table.columns {
+        Columns::SingleConstructor(columns) if constructor_index == 0 => (None, columns),
+        Columns::MultipleConstructors(constructors) => constructors
+            .iter()
+            .nth(usize::from(constructor_index))
+            .map(|(constructor, columns)| (Some(constructor.clone()), columns))
+            .ok_or_else(|| format!("unknown constructor index {constructor_index}"))?,
+        Columns::SingleConstructor(_) => {
+            return Err(format!("unknown constructor index {constructor_index}"))
+        }
+    };
+
+    let mut values = BTreeMap::new();
+    for (column_name, scalar_type) in columns {
+        let value = match scalar_type {
+            ScalarType::Int => ScalarValue::Int(i32::from_le_bytes(reader.take_array()?)),
+            ScalarType::Bool => ScalarValue::Bool(reader.take(1)?[0] != 0),
+            ScalarType::String => {
+                let len = u32::from_le_bytes(reader.take_array()?);
+                let raw = reader.take(usize::try_from(len).map_err(|e| e.to_string())?)?;
+                let string = std::str::from_utf8(raw).map_err(|e| e.to_string())?;
+                ScalarValue::String(string.to_string())
+            }
+        };
+        values.insert(column_name.clone(), value);
+    }
+
+    if !reader.bytes.is_empty() {
+        return Err(format!(
+            "{} unexpected bytes at end of row",
+            reader.bytes.len()
+        ));
+    }
+
+    Ok(Row {
+        constructor,
+        values,
+    })
+}
+
+/// rows used to be stored as JSON objects, with the constructor in `_type`
+fn decode_json_row(bytes: &[u8]) -> Result<Row, String> {
+    let json: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+
+    let mut constructor = None;
+    let mut values = BTreeMap::new();
+
+    for (key, value) in json {
+        let scalar_value = match value {
+            serde_json::Value::String(s) if key == "_type" => {
+                constructor = Some(Constructor(s));
+                continue;
+            }
+            serde_json::Value::String(s) => ScalarValue::String(s),
+            serde_json::Value::Bool(b) => ScalarValue::Bool(b),
+            serde_json::Value::Number(n) => n
+                .as_i64()
+                .and_then(|i| i32::try_from(i).ok())
+                .map(ScalarValue::Int)
+                .ok_or_else(|| format!("column {key} is not an Int"))?,
+            serde_json::Value::Null => ScalarValue::Null,
+            _ => return Err(format!("column {key} is not a scalar value")),
+        };
+        values.insert(ColumnName(key), scalar_value);
+    }
+
+    Ok(Row {
+        constructor,
+        values,
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() < len {
+            return Err("row ended unexpectedly".to_string());
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::{decode_row, encode_row, Row};
+    use engine_core::{ColumnName, Constructor, ScalarValue};
+    use std::collections::BTreeMap;
+
+    fn pet_row() -> Row {
+        let mut values = BTreeMap::new();
+        values.insert(ColumnName("age".to_string()), ScalarValue::Int(21));
+        values.insert(
+            ColumnName("name".to_string()),
+            ScalarValue::String("Mr Dog".to_string()),
+        );
+        values.insert(
+            ColumnName("likes_stick".to_string()),
+            ScalarValue::Bool(true),
+        );
+        Row {
+            constructor: Some(Constructor("Dog".to_string())),
+            values,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (_, table) = engine_core::parse_table(
+            "type Pet { Cat { age: Int, name: String }, Dog { age: Int, name: String, likes_stick: Bool } }",
+        )
+        .expect("parse_table");
+
+        let bytes = encode_row(&table, &pet_row()).expect("encode_row");
+
+        // version, constructor, age, likes_stick, name length, name
+        assert_eq!(bytes.len(), 1 + 1 + 4 + 1 + 4 + 6);
+        assert_eq!(decode_row(&table, &bytes), Ok(pet_row()));
+    }
+
+    #[test]
+    fn test_legacy_json_row() {
+        let (_, table) = engine_core::parse_table(
+            "type Pet { Cat { age: Int, name: String }, Dog { age: Int, name: String, likes_stick: Bool } }",
+        )
+        .expect("parse_table");
+
+        assert_eq!(
+            decode_row(
+                &table,
+                b"{\"_type\":\"Dog\",\"age\":21,\"likes_stick\":true,\"name\":\"Mr Dog\"}"
+            ),
+            Ok(pet_row())
+        );
+    }
+
+    #[test]
+    fn test_truncated_row() {
+        let (_, table) = engine_core::parse_table("type User { age: Int }").expect("parse_table");
+
+        assert_eq!(
+            decode_row(&table, &[1, 0, 27]),
+            Err("row ended unexpectedly".to_string())
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use engine_core::{
+    AlterError, CatalogError, DeleteError, InsertError, SelectError, TableName, TransactionCommand,
+    TypeErrors, UpdateError,
+};
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum EngineError {
+    #[error("storage error: {0}")]
+    Storage(String),
+    #[error("corrupt row {key:}: {reason:}")]
+    CorruptRow { key: String, reason: String },
+    #[error("corrupt schema for table {table_name:}: {reason:}")]
+    CorruptSchema {
+        table_name: TableName,
+        reason: String,
+    },
+    #[error("transaction conflict on key {key:}")]
+    TransactionConflict { key: String },
+    #[error("a transaction has already begun")]
+    TransactionInProgress,
+    #[error("`{0}` without a `begin` before it")]
+    NoTransaction(TransactionCommand),
+    #[error("an earlier statement failed, so the transaction can only be rolled back")]
+    TransactionAborted,
+    #[error("the transaction was never committed")]
+    TransactionNotCommitted,
+    #[error("`{0}` can only be used in a script or session")]
+    TransactionOutsideSession(TransactionCommand),
+    #[error("every key for table {0} has been used")]
+    SequenceExhausted(TableName),
+    #[error("{0}")]
+    Insert(#[from] InsertError),
+    #[error("{} rows could not be inserted", .failures.len())]
+    InvalidRows {
+        /// the index of each bad row in the input, and what was wrong with it
+        failures: Vec<(usize, InsertError)>,
+    },
+    #[error("{0}")]
+    Select(#[from] SelectError),
+    #[error("{0}")]
+    Delete(#[from] DeleteError),
+    #[error("{0}")]
+    Update(#[from] UpdateError),
+    #[error("{0}")]
+    Alter(#[from] AlterError),
+    #[error("{0}")]
+    Catalog(#[from] CatalogError),
+}
+
+impl EngineError {
+    /// the type errors, if the statement did not typecheck
+    pub fn type_errors(&self) -> Option<&TypeErrors> {
+        match self {
+            EngineError::Select(SelectError::TypeError(errors))
+            | EngineError::Insert(InsertError::TypeError(errors))
+            | EngineError::Update(UpdateError::TypeError(errors))
+            | EngineError::Delete(DeleteError::TypeError(errors))
+            | EngineError::Alter(AlterError::TypeError(errors))
+            | EngineError::Catalog(CatalogError::TypeError(errors)) => Some(errors),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+impl From<rocksdb::Error> for EngineError {
+    fn from(error: rocksdb::Error) -> Self {
+        EngineError::Storage(error.into_string())
+    }
+}
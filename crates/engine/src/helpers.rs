@@ -1,38 +1,21 @@
 use engine_core::{
-    and, equals, ColumnName, Comparison, Expression, Function, ScalarValue, SelectColumns,
+    ColumnName, Comparison, Constructor, Expression, Function, ScalarValue, SelectColumns,
 };
+use std::collections::BTreeMap;
 
 pub fn matches_prefix(prefix: &str, key: &[u8]) -> bool {
-    let key_string = std::str::from_utf8(key).unwrap();
-    let prefix_len = prefix.len();
     // only do check if key is longer than prefix
-    if prefix_len < key_string.len() {
-        let key_start = &key_string.get(0..prefix.len()).unwrap();
-        key_start == &prefix
-    } else {
-        false
-    }
+    prefix.len() < key.len() && key.starts_with(prefix.as_bytes())
 }
 
-pub fn add_constructor_to_expression(
-    columns: SelectColumns,
-    r#where: Expression,
-) -> (Expression, Vec<ColumnName>) {
+/// the constructor we are matching on (if any) and the columns we want
+pub fn split_select_columns(columns: SelectColumns) -> (Option<Constructor>, Vec<ColumnName>) {
     match columns {
-        SelectColumns::SelectColumns { columns } => (r#where, columns),
+        SelectColumns::SelectColumns { columns } => (None, columns),
         SelectColumns::SelectConstructor {
             constructor,
             columns,
-        } => (
-            and(
-                r#where,
-                equals(
-                    ColumnName("_type".to_string()),
-                    ScalarValue::String(constructor.to_string()),
-                ),
-            ),
-            columns,
-        ),
+        } => (Some(constructor), columns),
     }
 }
 
@@ -44,6 +27,7 @@ fn bool_expr(bool: bool) -> Expression {
     Expression::Bool(bool)
 }
 
+/// a value as JSON, with `Null` as `null`
 pub fn to_serde_json(scalar_value: &ScalarValue) -> serde_json::Value {
     match scalar_value {
         ScalarValue::Int(i) => serde_json::Value::Number(serde_json::Number::from(*i)),
@@ -54,13 +38,15 @@ pub fn to_serde_json(scalar_value: &ScalarValue) -> serde_json::Value {
 }
 
 // given a row and an expression, evaluate it
-pub fn apply_expression(result: &serde_json::Value, expression: &Expression) -> Expression {
+pub fn apply_expression(
+    result: &BTreeMap<ColumnName, ScalarValue>,
+    expression: &Expression,
+) -> Expression {
     match expression {
         Expression::Comparison(Comparison { column, value }) => {
-            let json_object = result.as_object().unwrap();
-            let column_value = json_object.get(&column.to_string()).unwrap();
-            let json_value = to_serde_json(value);
-            bool_expr(*column_value == json_value)
+            // a column missing from this constructor is treated as `null`
+            let column_value = result.get(column).unwrap_or(&ScalarValue::Null);
+            bool_expr(column_value == value)
         }
         Expression::BinaryFunction {
             function,
@@ -1,9 +1,19 @@
+use crate::storage::Storage;
+use crate::transaction::{autocommit, Transaction};
+use crate::EngineError;
 use engine_core::{Insert, InsertError};
-use rocksdb::DB;
 use std::collections::BTreeMap;
 
-pub fn insert(db: &DB, insert: &Insert) -> Result<i32, InsertError> {
-    let table = match crate::data::lookup_table(db, &insert.table) {
+/// typecheck and store a row, returning its key
+pub fn insert(db: &impl Storage, insert: &Insert) -> Result<i32, EngineError> {
+    autocommit(db, |tx| insert_in(tx, insert))
+}
+
+pub(crate) fn insert_in(
+    tx: &mut Transaction<impl Storage>,
+    insert: &Insert,
+) -> Result<i32, EngineError> {
+    let table = match crate::data::lookup_table_for_write(tx, &insert.table)? {
         Some(table) => Ok(table),
         None => Err(InsertError::TableNotFound(insert.table.clone())),
     }?;
@@ -12,5 +22,309 @@ pub fn insert(db: &DB, insert: &Insert) -> Result<i32, InsertError> {
     tables.insert(insert.table.clone(), table);
 
     engine_core::typecheck_insert(&tables, insert).map_err(InsertError::TypeError)?;
-    Ok(crate::data::insert(db, insert))
+    crate::data::insert(tx, &tables[&insert.table], insert)
+}
+
+/// typecheck and store a lot of rows at once, returning their keys in order.
+/// every row is checked before anything is written, and then they are all
+/// written in a single batch, so either all of them go in or none do
+pub fn insert_many(db: &impl Storage, inserts: &[Insert]) -> Result<Vec<i32>, EngineError> {
+    autocommit(db, |tx| insert_many_in(tx, inserts))
+}
+
+fn insert_many_in(
+    tx: &mut Transaction<impl Storage>,
+    inserts: &[Insert],
+) -> Result<Vec<i32>, EngineError> {
+    // look up each table once, rather than once per row
+    let mut tables = BTreeMap::new();
+    for insert in inserts {
+        if !tables.contains_key(&insert.table) {
+            if let Some(table) = crate::data::lookup_table_for_write(tx, &insert.table)? {
+                tables.insert(insert.table.clone(), table);
+            }
+        }
+    }
+
+    let mut failures = vec![];
+    for (index, insert) in inserts.iter().enumerate() {
+        if !tables.contains_key(&insert.table) {
+            failures.push((index, InsertError::TableNotFound(insert.table.clone())));
+        } else if let Err(type_error) = engine_core::typecheck_insert(&tables, insert) {
+            failures.push((index, InsertError::TypeError(type_error)));
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(EngineError::InvalidRows { failures });
+    }
+
+    // duplicate keys can only be found once we start writing
+    let mut keys = Vec::with_capacity(inserts.len());
+    for (index, insert) in inserts.iter().enumerate() {
+        match crate::data::insert(tx, &tables[&insert.table], insert) {
+            Ok(key) => keys.push(key),
+            Err(EngineError::Insert(insert_error)) => failures.push((index, insert_error)),
+            Err(other) => return Err(other),
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(keys)
+    } else {
+        Err(EngineError::InvalidRows { failures })
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::{insert, insert_many};
+    use crate::data::{insert_table, sequence_key};
+    use crate::storage::{MemoryStorage, Storage};
+    use crate::EngineError;
+    use engine_core::{
+        ColumnName, Insert, InsertError, InsertValue, Location, OnConflict, ScalarType,
+        ScalarValue, TableName, Type, TypeError, TypeErrors,
+    };
+    use std::collections::BTreeMap;
+
+    fn user_insert(age: i32, on_conflict: OnConflict) -> Insert {
+        let mut values = BTreeMap::new();
+        values.insert(ColumnName("age".to_string()), ScalarValue::Int(age));
+
+        Insert {
+            table: TableName("User".to_string()),
+            key: Some(1),
+            value: InsertValue::Single { values },
+            on_conflict,
+            returning_key: false,
+        }
+    }
+
+    #[test]
+    fn test_duplicate_key() {
+        let db = MemoryStorage::default();
+        let (_, table) = engine_core::parse_table("type User { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+
+        assert_eq!(insert(&db, &user_insert(27, OnConflict::Fail)), Ok(1));
+        assert_eq!(
+            insert(&db, &user_insert(28, OnConflict::Fail)),
+            Err(EngineError::Insert(InsertError::DuplicateKey {
+                table: TableName("User".to_string()),
+                key: 1
+            }))
+        );
+    }
+
+    #[test]
+    fn test_upsert() {
+        let db = MemoryStorage::default();
+        let (_, table) = engine_core::parse_table("type User { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+
+        assert_eq!(insert(&db, &user_insert(27, OnConflict::Fail)), Ok(1));
+        assert_eq!(insert(&db, &user_insert(28, OnConflict::Replace)), Ok(1));
+
+        let (_, select_sql) =
+            engine_core::parse_select("select age from User").expect("parse_select");
+
+        assert_eq!(
+            crate::select::select(&db, select_sql).map(|result| result.rows),
+            Ok(vec![vec![ScalarValue::Int(28)]])
+        );
+    }
+
+    #[test]
+    fn test_auto_increment() {
+        let db = MemoryStorage::default();
+        let (_, table) =
+            engine_core::parse_table("type User autoincrement { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+
+        let (_, insert_sql) =
+            engine_core::parse_insert("insert into User { age: 27 } returning key")
+                .expect("parse_insert");
+
+        assert_eq!(insert(&db, &insert_sql), Ok(1));
+        assert_eq!(insert(&db, &insert_sql), Ok(2));
+
+        let (_, select_sql) =
+            engine_core::parse_select("select age from User").expect("parse_select");
+
+        assert_eq!(
+            crate::select::select(&db, select_sql).map(|result| result.rows),
+            Ok(vec![vec![ScalarValue::Int(27)], vec![ScalarValue::Int(27)]])
+        );
+    }
+
+    #[test]
+    fn test_sequence_exhausted() {
+        let db = MemoryStorage::default();
+        let (_, table) =
+            engine_core::parse_table("type User autoincrement { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+
+        let table_name = TableName("User".to_string());
+        db.put(
+            sequence_key(&table_name).as_bytes(),
+            i32::MAX.to_string().as_bytes(),
+        )
+        .expect("put");
+
+        let (_, insert_sql) =
+            engine_core::parse_insert("insert into User { age: 27 }").expect("parse_insert");
+
+        assert_eq!(
+            insert(&db, &insert_sql),
+            Err(EngineError::SequenceExhausted(table_name))
+        );
+    }
+
+    #[test]
+    fn test_insert_many() {
+        let db = MemoryStorage::default();
+        let (_, table) =
+            engine_core::parse_table("type User autoincrement { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+
+        let inserts: Vec<_> = (0..100)
+            .map(|age| {
+                let (_, insert_sql) =
+                    engine_core::parse_insert(&format!("insert into User {{ age: {age} }}"))
+                        .expect("parse_insert");
+                insert_sql
+            })
+            .collect();
+
+        assert_eq!(insert_many(&db, &inserts), Ok((1..=100).collect()));
+
+        let (_, select_sql) =
+            engine_core::parse_select("select age from User where age = 99").expect("parse_select");
+        assert_eq!(
+            crate::select::select(&db, select_sql).map(|result| result.rows.len()),
+            Ok(1)
+        );
+    }
+
+    #[test]
+    fn test_insert_many_reports_every_bad_row() {
+        let db = MemoryStorage::default();
+        let (_, table) = engine_core::parse_table("type User { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+
+        let inserts: Vec<_> = [
+            "insert into User key 1 { age: 27 }",
+            "insert into User key 2 { age: true }",
+            "insert into Missing key 3 { age: 27 }",
+        ]
+        .iter()
+        .map(|insert_sql| {
+            engine_core::parse_insert(insert_sql)
+                .expect("parse_insert")
+                .1
+        })
+        .collect();
+
+        assert_eq!(
+            insert_many(&db, &inserts),
+            Err(EngineError::InvalidRows {
+                failures: vec![
+                    (
+                        1,
+                        InsertError::TypeError(TypeErrors(vec![(
+                            Location::Column(ColumnName("age".to_string())),
+                            TypeError::TypeMismatchInInput {
+                                expected_type: Type::ScalarType(ScalarType::Int),
+                                input_value: ScalarValue::Bool(true)
+                            }
+                        )]))
+                    ),
+                    (
+                        2,
+                        InsertError::TableNotFound(TableName("Missing".to_string()))
+                    )
+                ]
+            })
+        );
+
+        let duplicates: Vec<_> = [
+            "insert into User key 1 { age: 27 }",
+            "insert into User key 1 { age: 28 }",
+        ]
+        .iter()
+        .map(|insert_sql| {
+            engine_core::parse_insert(insert_sql)
+                .expect("parse_insert")
+                .1
+        })
+        .collect();
+
+        assert_eq!(
+            insert_many(&db, &duplicates),
+            Err(EngineError::InvalidRows {
+                failures: vec![(
+                    1,
+                    InsertError::DuplicateKey {
+                        table: TableName("User".to_string()),
+                        key: 1
+                    }
+                )]
+            })
+        );
+
+        // nothing was written
+        let (_, select_sql) =
+            engine_core::parse_select("select age from User").expect("parse_select");
+        assert_eq!(
+            crate::select::select(&db, select_sql).map(|result| result.rows),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn test_defaults() {
+        let db = MemoryStorage::default();
+        let (_, table) =
+            engine_core::parse_table("type User autoincrement { age: Int = 0, nice: Bool }")
+                .expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+
+        let (_, insert_sql) =
+            engine_core::parse_insert("insert into User { nice: true }").expect("parse_insert");
+        assert_eq!(insert(&db, &insert_sql), Ok(1));
+
+        let (_, select_sql) =
+            engine_core::parse_select("select age, nice from User").expect("parse_select");
+        assert_eq!(
+            crate::select::select(&db, select_sql).map(|result| result.rows),
+            Ok(vec![vec![ScalarValue::Int(0), ScalarValue::Bool(true)]])
+        );
+    }
+
+    #[test]
+    fn test_defaults_per_constructor() {
+        let db = MemoryStorage::default();
+        let (_, table) = engine_core::parse_table(
+            "type Pet autoincrement { Cat { age: Int = 0, name: String }, Fish { age: Int = 1, name: String } }",
+        )
+        .expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+
+        for insert_sql in [
+            "insert into Pet Cat { name: \"Mr Cat\" }",
+            "insert into Pet Fish { name: \"Mr Fish\" }",
+        ] {
+            let (_, insert_sql) = engine_core::parse_insert(insert_sql).expect("parse_insert");
+            insert(&db, &insert_sql).expect("insert");
+        }
+
+        // each row gets the default declared in its own constructor
+        let (_, select_sql) =
+            engine_core::parse_select("select age from Pet").expect("parse_select");
+        assert_eq!(
+            crate::select::select(&db, select_sql).map(|result| result.rows),
+            Ok(vec![vec![ScalarValue::Int(0)], vec![ScalarValue::Int(1)]])
+        );
+    }
 }
@@ -1,4 +1,20 @@
+pub mod alter;
+pub mod catalog;
 pub mod data;
+pub mod delete;
+pub mod encoding;
+mod error;
 mod helpers;
 pub mod insert;
+pub mod query;
+pub mod script;
 pub mod select;
+mod shape;
+pub mod storage;
+pub mod transaction;
+pub mod update;
+
+pub use error::EngineError;
+pub use helpers::to_serde_json;
+pub use query::{query, QueryError, ShapeError};
+pub use transaction::{begin, Transaction};
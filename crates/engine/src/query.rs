@@ -0,0 +1,406 @@
+//! reading the rows of a `select` straight into Rust types with serde
+use crate::helpers::to_serde_json;
+use crate::select::{select_with_constructors, Schema};
+use crate::shape::{shape, FieldShape, Fields, Shape};
+use crate::storage::Storage;
+use crate::EngineError;
+use engine_core::{
+    parse_statement, ColumnName, Columns, Constructor, ParseError, ScalarType, ScalarValue,
+    SelectColumns, Statement, Table, TableName, Type,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum QueryError {
+    #[error("{0}")]
+    Parse(ParseError),
+    #[error("only a `select` can be queried")]
+    NotASelect,
+    #[error("{0}")]
+    Engine(#[from] EngineError),
+    #[error("{0}")]
+    Shape(#[from] ShapeError),
+    #[error("row {} could not be deserialized: {message:}", .row + 1)]
+    Deserialize {
+        /// which row failed, counting from zero
+        row: usize,
+        message: String,
+    },
+}
+
+/// the Rust type does not fit the rows the query returns
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum ShapeError {
+    #[error("rows cannot be read into {type_name:}: {reason:}")]
+    Unsupported { type_name: String, reason: String },
+    #[error("field {field:} is not one of the selected columns")]
+    MissingColumn { field: String },
+    #[error("field {field:} is {found:}, but the column is {expected:}")]
+    TypeMismatch {
+        field: String,
+        expected: ScalarType,
+        found: String,
+    },
+    #[error("column {field:} can be null, so field {field:} has to be an `Option`")]
+    NotOptional { field: String },
+    #[error("table {table:} has no constructors to read into an enum")]
+    NotSumType { table: TableName },
+    #[error("variant {variant:} is not a constructor of table {table:}")]
+    UnknownConstructor { table: TableName, variant: String },
+    #[error("field {field:} is not a column of constructor {constructor:}")]
+    NotInConstructor {
+        constructor: Constructor,
+        field: String,
+    },
+    #[error("there is no variant for rows with constructor {constructor:}")]
+    MissingVariant { constructor: Constructor },
+}
+
+/// run a `select` and deserialize each row into a `T`.
+///
+/// a struct takes each field from the column with the same name. an enum takes
+/// the variant named after the constructor the row was stored with, which has
+/// to have named fields or hold a struct. `T` is checked against the types of
+/// the selected columns before any rows are read.
+///
+/// fields whose type rejects `0`, `false` or `""`, like `NonZeroI32`, make `T`
+/// unsupported, as checking it means building one out of those values
+pub fn query<T: DeserializeOwned>(db: &impl Storage, source: &str) -> Result<Vec<T>, QueryError> {
+    let Statement::Select(select) = parse_statement(source).map_err(QueryError::Parse)? else {
+        return Err(QueryError::NotASelect);
+    };
+    let selected = match &select.columns {
+        SelectColumns::SelectConstructor { constructor, .. } => Some(constructor.clone()),
+        SelectColumns::SelectColumns { .. } => None,
+    };
+
+    let (table, schema, rows) = select_with_constructors(db, select)?;
+    let shape = shape::<T>().map_err(|reason| ShapeError::Unsupported {
+        type_name: std::any::type_name::<T>().to_string(),
+        reason,
+    })?;
+    check_shape(&shape, &schema, &table, selected.as_ref())?;
+
+    rows.enumerate()
+        .map(|(index, row)| {
+            let (constructor, values) = row?;
+            serde_json::from_value(row_json(&shape, &schema, constructor, &values)).map_err(
+                |error| QueryError::Deserialize {
+                    row: index,
+                    message: error.to_string(),
+                },
+            )
+        })
+        .collect()
+}
+
+fn check_shape(
+    shape: &Shape,
+    schema: &Schema,
+    table: &Table,
+    selected: Option<&Constructor>,
+) -> Result<(), ShapeError> {
+    match shape {
+        Shape::Any => Ok(()),
+        Shape::Struct(fields) => fields.iter().try_for_each(|(field, field_shape)| {
+            check_field(field, field_shape, column_type(schema, field)?)
+        }),
+        Shape::Enum(variants) => {
+            let Columns::MultipleConstructors(constructors) = &table.columns else {
+                return Err(ShapeError::NotSumType {
+                    table: table.name.clone(),
+                });
+            };
+
+            for (variant, fields) in variants {
+                let constructor = Constructor((*variant).to_string());
+                let Some(columns) = constructors.get(&constructor) else {
+                    return Err(ShapeError::UnknownConstructor {
+                        table: table.name.clone(),
+                        variant: (*variant).to_string(),
+                    });
+                };
+                // no rows will come from the other constructors, so their
+                // columns don't have to be selected
+                if selected.is_some_and(|selected| *selected != constructor) {
+                    continue;
+                }
+                for (field, field_shape) in fields {
+                    // it has to be selected, but within its variant it is never null
+                    column_type(schema, field)?;
+                    let Some(scalar_type) = columns.get(&ColumnName((*field).to_string())) else {
+                        return Err(ShapeError::NotInConstructor {
+                            constructor,
+                            field: (*field).to_string(),
+                        });
+                    };
+                    check_field(field, field_shape, &Type::ScalarType(scalar_type.clone()))?;
+                }
+            }
+
+            // every row the query can return needs a variant to go in
+            match constructors.keys().find(|constructor| {
+                (selected.is_none() || selected == Some(*constructor))
+                    && !variants
+                        .iter()
+                        .any(|(variant, _)| *variant == constructor.0)
+            }) {
+                Some(constructor) => Err(ShapeError::MissingVariant {
+                    constructor: constructor.clone(),
+                }),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+fn column_type<'a>(schema: &'a Schema, field: &str) -> Result<&'a Type, ShapeError> {
+    schema
+        .iter()
+        .find(|(column, _)| column.0 == field)
+        .map(|(_, r#type)| r#type)
+        .ok_or_else(|| ShapeError::MissingColumn {
+            field: field.to_string(),
+        })
+}
+
+fn check_field(field: &str, shape: &FieldShape, r#type: &Type) -> Result<(), ShapeError> {
+    match (shape, r#type) {
+        (FieldShape::Any, _)
+        | (FieldShape::Bool, Type::ScalarType(ScalarType::Bool))
+        | (FieldShape::Int, Type::ScalarType(ScalarType::Int))
+        | (FieldShape::String, Type::ScalarType(ScalarType::String)) => Ok(()),
+        (FieldShape::Option(inner), Type::Optional(inner_type)) => {
+            check_field(field, inner, inner_type)
+        }
+        (FieldShape::Option(inner), _) => check_field(field, inner, r#type),
+        (_, Type::Optional(_)) => Err(ShapeError::NotOptional {
+            field: field.to_string(),
+        }),
+        (found, Type::ScalarType(expected)) => Err(ShapeError::TypeMismatch {
+            field: field.to_string(),
+            expected: expected.clone(),
+            found: found.to_string(),
+        }),
+    }
+}
+
+// the row as the JSON that serde would expect for the shape: an object of the
+// fields, wrapped in the name of the variant for an enum
+fn row_json(
+    shape: &Shape,
+    schema: &Schema,
+    constructor: Option<Constructor>,
+    values: &[ScalarValue],
+) -> Value {
+    let columns = schema
+        .iter()
+        .zip(values)
+        .map(|((column, _), value)| (column.0.clone(), to_serde_json(value)));
+    let object = |fields: &Fields| {
+        Value::Object(
+            columns
+                .clone()
+                .filter(|(column, _)| fields.iter().any(|(field, _)| field == column))
+                .collect(),
+        )
+    };
+
+    match shape {
+        Shape::Any => Value::Object(columns.clone().collect()),
+        Shape::Struct(fields) => object(fields),
+        Shape::Enum(variants) => {
+            let variant = constructor
+                .map(|constructor| constructor.0)
+                .unwrap_or_default();
+            let fields = variants
+                .iter()
+                .find(|(name, _)| *name == variant)
+                .map(|(_, fields)| object(fields))
+                .unwrap_or_default();
+            Value::Object(Map::from_iter([(variant, fields)]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::{query, QueryError, ShapeError};
+    use crate::script::execute_script;
+    use crate::storage::MemoryStorage;
+    use engine_core::{Constructor, ScalarType, TableName};
+    use serde_derive::Deserialize;
+
+    fn pets() -> MemoryStorage {
+        let db = MemoryStorage::default();
+        execute_script(
+            &db,
+            "type Pet {
+                Cat { age: Int, name: String },
+                Dog { age: Int, name: String, likes_stick: Bool }
+            };
+            insert into Pet key 1 Cat { age: 3, name: \"Mimsy\" };
+            insert into Pet key 2 Dog { age: 5, name: \"Rex\", likes_stick: true };",
+        )
+        .expect("execute_script");
+        db
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct PetRow {
+        age: i32,
+        name: String,
+        likes_stick: Option<bool>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Cat {
+        age: i32,
+        name: String,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Pet {
+        Cat(Cat),
+        Dog { name: String, likes_stick: bool },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Named {
+        name: i32,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct NonZeroAge {
+        age: std::num::NonZeroI32,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Stick {
+        likes_stick: bool,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum OnlyCats {
+        Cat(Cat),
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Animal {
+        Cat(Cat),
+        Horse { name: String },
+    }
+
+    #[test]
+    fn test_query_struct() {
+        let db = pets();
+
+        assert_eq!(
+            query::<PetRow>(&db, "select age, name, likes_stick from Pet"),
+            Ok(vec![
+                PetRow {
+                    age: 3,
+                    name: "Mimsy".to_string(),
+                    likes_stick: None
+                },
+                PetRow {
+                    age: 5,
+                    name: "Rex".to_string(),
+                    likes_stick: Some(true)
+                }
+            ])
+        );
+
+        // only cats, so the fields only need to be in `Cat`
+        assert_eq!(
+            query::<Cat>(&db, "select Cat { age, name } from Pet"),
+            Ok(vec![Cat {
+                age: 3,
+                name: "Mimsy".to_string()
+            }])
+        );
+    }
+
+    #[test]
+    fn test_query_enum() {
+        let db = pets();
+
+        assert_eq!(
+            query::<Pet>(&db, "select age, name, likes_stick from Pet"),
+            Ok(vec![
+                Pet::Cat(Cat {
+                    age: 3,
+                    name: "Mimsy".to_string()
+                }),
+                Pet::Dog {
+                    name: "Rex".to_string(),
+                    likes_stick: true
+                }
+            ])
+        );
+
+        // `Dog` wants `likes_stick`, but only cats can be returned
+        assert_eq!(
+            query::<Pet>(&db, "select Cat { age, name } from Pet"),
+            Ok(vec![Pet::Cat(Cat {
+                age: 3,
+                name: "Mimsy".to_string()
+            })])
+        );
+    }
+
+    #[test]
+    fn test_query_shape_errors() {
+        let db = pets();
+        assert_eq!(
+            query::<Named>(&db, "select name from Pet"),
+            Err(QueryError::Shape(ShapeError::TypeMismatch {
+                field: "name".to_string(),
+                expected: ScalarType::String,
+                found: "an integer".to_string()
+            }))
+        );
+        assert_eq!(
+            query::<Stick>(&db, "select likes_stick from Pet"),
+            Err(QueryError::Shape(ShapeError::NotOptional {
+                field: "likes_stick".to_string()
+            }))
+        );
+
+        assert_eq!(
+            query::<Cat>(&db, "select name from Pet"),
+            Err(QueryError::Shape(ShapeError::MissingColumn {
+                field: "age".to_string()
+            }))
+        );
+        assert_eq!(
+            query::<OnlyCats>(&db, "select age, name from Pet"),
+            Err(QueryError::Shape(ShapeError::MissingVariant {
+                constructor: Constructor("Dog".to_string())
+            }))
+        );
+        assert!(query::<OnlyCats>(&db, "select Cat { age, name } from Pet").is_ok());
+        assert_eq!(
+            query::<Animal>(&db, "select age, name from Pet"),
+            Err(QueryError::Shape(ShapeError::UnknownConstructor {
+                table: TableName("Pet".to_string()),
+                variant: "Horse".to_string()
+            }))
+        );
+
+        assert!(matches!(
+            query::<i32>(&db, "select age from Pet"),
+            Err(QueryError::Shape(ShapeError::Unsupported { .. }))
+        ));
+        // every age is above zero, but the shape is traced with a zero
+        assert!(matches!(
+            query::<NonZeroAge>(&db, "select age from Pet"),
+            Err(QueryError::Shape(ShapeError::Unsupported { .. }))
+        ));
+        assert_eq!(
+            query::<Cat>(&db, "delete from Pet"),
+            Err(QueryError::NotASelect)
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! running statements, one at a time or a whole script of them
+use crate::select::ResultSet;
+use crate::storage::Storage;
+use crate::transaction::{begin, Transaction};
+use crate::EngineError;
+use engine_core::{
+    CatalogCommand, Insert, ParseError, Span, Statement, Table, TableName, TransactionCommand,
+};
+
+/// what running a statement produced
+#[derive(Debug, PartialEq)]
+pub enum Output {
+    /// a `type` was declared
+    TableCreated,
+    /// the rows matched by a `select`
+    Rows(ResultSet),
+    /// the key of an inserted row
+    Inserted(i32),
+    /// how many rows were updated, deleted, rewritten by `alter type` or dropped
+    RowsAffected(usize),
+    /// `show tables`
+    Tables(Vec<TableName>),
+    /// `describe`
+    Table(Table),
+    /// `begin`, `commit` or `rollback`
+    Transaction(TransactionCommand),
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum ScriptError {
+    #[error("{0}")]
+    Parse(ParseError),
+    #[error("statement {} failed: {error:}", .index + 1)]
+    Statement {
+        /// which statement in the script failed, counting from zero
+        index: usize,
+        /// where the statement is in the script
+        span: Span,
+        error: EngineError,
+    },
+}
+
+/// run a single statement in its own commit
+pub fn execute(db: &impl Storage, statement: Statement) -> Result<Output, EngineError> {
+    match statement {
+        Statement::Table(table) => {
+            crate::data::insert_table(db, &table)?;
+            Ok(Output::TableCreated)
+        }
+        Statement::Select(select) => crate::select::select(db, select).map(Output::Rows),
+        Statement::Insert(insert) => {
+            crate::insert::insert(db, &insert).map(|key| inserted(&insert, key))
+        }
+        Statement::Update(update) => crate::update::update(db, &update).map(Output::RowsAffected),
+        Statement::Delete(delete) => crate::delete::delete(db, &delete).map(Output::RowsAffected),
+        Statement::Alter(alter) => crate::alter::alter_table(db, &alter).map(Output::RowsAffected),
+        Statement::Catalog(CatalogCommand::ShowTables) => {
+            crate::catalog::list_tables(db).map(Output::Tables)
+        }
+        Statement::Catalog(CatalogCommand::Describe { table }) => {
+            crate::catalog::describe_table(db, &table).map(Output::Table)
+        }
+        Statement::Catalog(CatalogCommand::DropTable { table }) => {
+            crate::catalog::drop_table(db, &table).map(Output::RowsAffected)
+        }
+        // there is nowhere to keep a transaction between statements
+        Statement::Transaction(command) => Err(EngineError::TransactionOutsideSession(command)),
+    }
+}
+
+// the key is only given back when the insert asked for it with `returning key`
+fn inserted(insert: &Insert, key: i32) -> Output {
+    if insert.returning_key {
+        Output::Inserted(key)
+    } else {
+        Output::RowsAffected(1)
+    }
+}
+
+/// run a statement as part of a transaction
+fn execute_in(
+    tx: &mut Transaction<impl Storage>,
+    statement: Statement,
+) -> Result<Output, EngineError> {
+    match statement {
+        Statement::Table(table) => {
+            tx.insert_table(&table)?;
+            Ok(Output::TableCreated)
+        }
+        Statement::Select(select) => tx.select(select).map(Output::Rows),
+        Statement::Insert(insert) => tx.insert(&insert).map(|key| inserted(&insert, key)),
+        Statement::Update(update) => tx.update(&update).map(Output::RowsAffected),
+        Statement::Delete(delete) => tx.delete(&delete).map(Output::RowsAffected),
+        Statement::Alter(alter) => tx.alter_table(&alter).map(Output::RowsAffected),
+        Statement::Catalog(CatalogCommand::ShowTables) => {
+            crate::catalog::list_tables_in(tx).map(Output::Tables)
+        }
+        Statement::Catalog(CatalogCommand::Describe { table }) => {
+            crate::catalog::describe_table_in(tx, &table).map(Output::Table)
+        }
+        Statement::Catalog(CatalogCommand::DropTable { table }) => {
+            tx.drop_table(&table).map(Output::RowsAffected)
+        }
+        // `Session` deals with these before they get here
+        Statement::Transaction(_) => Err(EngineError::TransactionInProgress),
+    }
+}
+
+/// runs statements one after another like `execute`, except that everything
+/// between `begin` and `commit` happens in a single transaction
+pub struct Session<'a, S> {
+    db: &'a S,
+    transaction: Option<Transaction<'a, S>>,
+    /// a statement in the transaction failed, and it has not been ended yet
+    aborted: bool,
+}
+
+impl<'a, S: Storage> Session<'a, S> {
+    pub fn new(db: &'a S) -> Self {
+        Session {
+            db,
+            transaction: None,
+            aborted: false,
+        }
+    }
+
+    /// whether a transaction has begun, and not been committed or rolled back yet
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some() || self.aborted
+    }
+
+    /// whether a statement in the transaction has failed, so that everything
+    /// until `commit` or `rollback` is rejected
+    pub fn is_aborted(&self) -> bool {
+        self.aborted
+    }
+
+    /// run a statement, as part of the transaction if there is one.
+    /// if a statement in a transaction fails, the whole transaction is rolled back,
+    /// and every statement after it fails until `commit` or `rollback` ends it.
+    /// a `commit` then rolls back, since there is nothing left to commit
+    pub fn execute(&mut self, statement: Statement) -> Result<Output, EngineError> {
+        if self.aborted {
+            return match statement {
+                Statement::Transaction(
+                    TransactionCommand::Commit | TransactionCommand::Rollback,
+                ) => {
+                    self.aborted = false;
+                    Ok(Output::Transaction(TransactionCommand::Rollback))
+                }
+                _ => Err(EngineError::TransactionAborted),
+            };
+        }
+
+        match (statement, self.transaction.take()) {
+            (Statement::Transaction(TransactionCommand::Begin), None) => {
+                self.transaction = Some(begin(self.db));
+                Ok(Output::Transaction(TransactionCommand::Begin))
+            }
+            // the transaction that is already open carries on
+            (Statement::Transaction(TransactionCommand::Begin), Some(tx)) => {
+                self.transaction = Some(tx);
+                Err(EngineError::TransactionInProgress)
+            }
+            (Statement::Transaction(TransactionCommand::Commit), Some(tx)) => tx
+                .commit()
+                .map(|()| Output::Transaction(TransactionCommand::Commit)),
+            (Statement::Transaction(TransactionCommand::Rollback), Some(tx)) => {
+                tx.rollback();
+                Ok(Output::Transaction(TransactionCommand::Rollback))
+            }
+            (Statement::Transaction(command), None) => Err(EngineError::NoTransaction(command)),
+            (statement, None) => execute(self.db, statement),
+            (statement, Some(mut tx)) => match execute_in(&mut tx, statement) {
+                Ok(output) => {
+                    self.transaction = Some(tx);
+                    Ok(output)
+                }
+                Err(error) => {
+                    self.aborted = true;
+                    Err(error)
+                }
+            },
+        }
+    }
+}
+
+/// parse a whole script, then run each statement in order.
+/// nothing runs if the script does not parse. statements before a failing one
+/// have already been committed, unless they are part of the same transaction
+pub fn execute_script(db: &impl Storage, source: &str) -> Result<Vec<Output>, ScriptError> {
+    let statements = engine_core::parse_script(source).map_err(ScriptError::Parse)?;
+
+    let mut session = Session::new(db);
+    let mut outputs = vec![];
+    // where the open transaction began, if there is one
+    let mut begun = None;
+    for (index, (span, statement, _)) in statements.into_iter().enumerate() {
+        if statement == Statement::Transaction(TransactionCommand::Begin)
+            && !session.in_transaction()
+        {
+            begun = Some((index, span));
+        }
+        let output = session
+            .execute(statement)
+            .map_err(|error| ScriptError::Statement { index, span, error })?;
+        outputs.push(output);
+    }
+
+    match begun {
+        Some((index, span)) if session.in_transaction() => Err(ScriptError::Statement {
+            index,
+            span,
+            error: EngineError::TransactionNotCommitted,
+        }),
+        _ => Ok(outputs),
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::{execute_script, Output, ScriptError, Session};
+    use crate::storage::MemoryStorage;
+    use crate::EngineError;
+    use engine_core::{InsertError, Span, TableName, TransactionCommand};
+
+    #[test]
+    fn test_execute_script() {
+        let db = MemoryStorage::default();
+        let script = "
+            -- everyone who has signed up
+            type User autoincrement { name: String };
+
+            insert into User { name: \"Egg\" } returning key; # the first one
+            insert into User { name: \"Horse\" };
+            /* check they made it */
+            show tables;
+            delete from User where name = \"Egg\"
+        ";
+
+        assert_eq!(
+            execute_script(&db, script),
+            Ok(vec![
+                Output::TableCreated,
+                Output::Inserted(1),
+                Output::RowsAffected(1),
+                Output::Tables(vec![TableName("User".to_string())]),
+                Output::RowsAffected(1),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_failing_statement() {
+        let db = MemoryStorage::default();
+        let script = "type User { name: String };
+            insert into User key 1 { name: \"Egg\" };
+            insert into User key 1 { name: \"Horse\" };
+            insert into User key 2 { name: \"Log\" };";
+
+        let Err(ScriptError::Statement { index, span, error }) = execute_script(&db, script) else {
+            panic!("expected the script to fail")
+        };
+        assert_eq!(index, 2);
+        assert_eq!(
+            &script[span.start..span.end],
+            "insert into User key 1 { name: \"Horse\" }"
+        );
+        assert_eq!(
+            error,
+            EngineError::Insert(InsertError::DuplicateKey {
+                table: TableName("User".to_string()),
+                key: 1
+            })
+        );
+
+        // the statements before it stay, but the ones after it never run
+        let (_, select) = engine_core::parse_select("select name from User").expect("parse_select");
+        assert_eq!(
+            crate::select::select(&db, select).map(|result| result.rows.len()),
+            Ok(1)
+        );
+    }
+
+    #[test]
+    fn test_script_parse_error() {
+        let db = MemoryStorage::default();
+
+        let Err(ScriptError::Parse(error)) =
+            execute_script(&db, "type User { name: String };\nselect name User")
+        else {
+            panic!("expected a parse error")
+        };
+        assert_eq!(error.message, "expected `from` after column list");
+
+        // nothing ran
+        assert_eq!(crate::catalog::list_tables(&db), Ok(vec![]));
+    }
+
+    fn count_rows(db: &MemoryStorage, select_sql: &str) -> usize {
+        let (_, select) = engine_core::parse_select(select_sql).expect("parse_select");
+        crate::select::select(db, select)
+            .map(|result| result.rows.len())
+            .expect("select")
+    }
+
+    #[test]
+    fn test_transaction() {
+        let db = MemoryStorage::default();
+        let script = "
+            begin;
+            type Order autoincrement { customer: String };
+            type LineItem autoincrement { order: Int, item: String };
+            insert into Order { customer: \"Egg\" } returning key;
+            insert into LineItem { order: 1, item: \"Horse\" };
+            commit;
+        ";
+
+        assert_eq!(
+            execute_script(&db, script),
+            Ok(vec![
+                Output::Transaction(TransactionCommand::Begin),
+                Output::TableCreated,
+                Output::TableCreated,
+                Output::Inserted(1),
+                Output::RowsAffected(1),
+                Output::Transaction(TransactionCommand::Commit),
+            ])
+        );
+        assert_eq!(count_rows(&db, "select item from LineItem"), 1);
+    }
+
+    #[test]
+    fn test_failing_transaction() {
+        let db = MemoryStorage::default();
+        execute_script(
+            &db,
+            "type Order { customer: String }; type LineItem { item: String };",
+        )
+        .expect("execute_script");
+
+        // the order goes too, as it was in the same transaction
+        let script = "begin;
+            insert into Order key 1 { customer: \"Egg\" };
+            insert into LineItem key 1 { item: 1 };
+            commit;";
+        let Err(ScriptError::Statement { index, .. }) = execute_script(&db, script) else {
+            panic!("expected the script to fail")
+        };
+        assert_eq!(index, 2);
+        assert_eq!(count_rows(&db, "select customer from Order"), 0);
+
+        // and so does one that never commits
+        let script = "begin; insert into Order key 1 { customer: \"Egg\" }";
+        assert_eq!(
+            execute_script(&db, script),
+            Err(ScriptError::Statement {
+                index: 0,
+                span: Span { start: 0, end: 5 },
+                error: EngineError::TransactionNotCommitted
+            })
+        );
+        assert_eq!(count_rows(&db, "select customer from Order"), 0);
+
+        // `rollback` throws everything away on purpose
+        let script = "begin; insert into Order key 1 { customer: \"Egg\" }; rollback";
+        assert!(execute_script(&db, script).is_ok());
+        assert_eq!(count_rows(&db, "select customer from Order"), 0);
+
+        let Err(ScriptError::Statement { error, .. }) = execute_script(&db, "commit") else {
+            panic!("expected the script to fail")
+        };
+        assert_eq!(
+            error,
+            EngineError::NoTransaction(TransactionCommand::Commit)
+        );
+    }
+
+    #[test]
+    fn test_begin_twice() {
+        let db = MemoryStorage::default();
+        execute_script(&db, "type Order { customer: String }").expect("execute_script");
+
+        let mut session = Session::new(&db);
+        for sql in ["begin", "insert into Order key 1 { customer: \"Egg\" }"] {
+            let statement = engine_core::parse_statement(sql).expect("parse_statement");
+            session.execute(statement).expect("execute");
+        }
+
+        let statement = engine_core::parse_statement("begin").expect("parse_statement");
+        assert_eq!(
+            session.execute(statement),
+            Err(EngineError::TransactionInProgress)
+        );
+        assert!(session.in_transaction());
+
+        let statement = engine_core::parse_statement("commit").expect("parse_statement");
+        assert_eq!(
+            session.execute(statement),
+            Ok(Output::Transaction(TransactionCommand::Commit))
+        );
+        assert_eq!(count_rows(&db, "select customer from Order"), 1);
+    }
+
+    #[test]
+    fn test_aborted_transaction() {
+        let db = MemoryStorage::default();
+        execute_script(&db, "type Order { customer: String }").expect("execute_script");
+
+        let mut session = Session::new(&db);
+        let mut run = |sql| session.execute(engine_core::parse_statement(sql).expect("parse"));
+        assert!(run("begin").is_ok());
+        assert!(run("insert into Order key 1 { customer: \"Egg\" }").is_ok());
+        assert!(run("insert into Order key 1 { customer: \"Horse\" }").is_err());
+
+        // nothing runs, and nothing is committed behind the transaction's back
+        assert_eq!(
+            run("insert into Order key 2 { customer: \"Log\" }"),
+            Err(EngineError::TransactionAborted)
+        );
+        assert_eq!(
+            run("commit"),
+            Ok(Output::Transaction(TransactionCommand::Rollback))
+        );
+        assert_eq!(count_rows(&db, "select customer from Order"), 0);
+
+        // the next statement is back to committing on its own
+        assert_eq!(
+            run("insert into Order key 2 { customer: \"Log\" }"),
+            Ok(Output::RowsAffected(1))
+        );
+        assert_eq!(count_rows(&db, "select customer from Order"), 1);
+    }
+}
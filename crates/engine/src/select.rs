@@ -1,85 +1,208 @@
-use super::data::lookup_table;
-use super::helpers::{add_constructor_to_expression, apply_expression, is_true, matches_prefix};
+use super::data::{lookup_table, lookup_table_in, read_row, scan_table, scan_table_in};
+use super::helpers::{apply_expression, is_true, split_select_columns, to_serde_json};
+use super::storage::Storage;
+use crate::transaction::Transaction;
+use crate::EngineError;
 use engine_core::typecheck_select;
-use engine_core::{Select, SelectError};
-use rocksdb::DB;
+use engine_core::{
+    ColumnName, Constructor, Expression, ScalarValue, Select, SelectError, Table, Type,
+};
 use serde_json::Value;
 use std::collections::BTreeMap;
-use std::str::FromStr;
 
-pub fn select(db: &DB, select: Select) -> Result<Vec<(usize, Value)>, SelectError> {
-    let table = match lookup_table(db, &select.table) {
-        Some(table) => Ok(table),
-        None => Err(SelectError::TableNotFound(select.table.clone())),
-    }?;
+/// the selected columns in order, each with the type the typechecker gave it
+pub type Schema = Vec<(ColumnName, Type)>;
 
-    let mut tables = BTreeMap::new();
-    tables.insert(select.table.clone(), table);
+/// the constructor a row was stored with, and the values selected from it
+pub(crate) type ConstructorRow = (Option<Constructor>, Vec<ScalarValue>);
 
-    typecheck_select(&tables, &select).map_err(SelectError::TypeError)?;
+/// the rows a `select` matched, along with what is in them
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultSet {
+    /// a column that only some constructors have is `Type::Optional`, and is
+    /// `ScalarValue::Null` in rows from the others
+    pub schema: Schema,
+    /// the values in each row, in the same order as `schema`
+    pub rows: Vec<Vec<ScalarValue>>,
+}
 
-    let prefix = format!("data_{}_", select.table);
-    let iter = db.prefix_iterator(prefix.clone());
-    let mut results = vec![];
+impl ResultSet {
+    /// each row as a JSON object of column names to values
+    pub fn to_json(&self) -> Vec<Value> {
+        self.rows
+            .iter()
+            .map(|row| {
+                Value::Object(
+                    self.schema
+                        .iter()
+                        .zip(row)
+                        .map(|((column, _), value)| (column.to_string(), to_serde_json(value)))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
 
-    // if we are using a constructor to match, add it to where clause
-    let (expression, columns) = add_constructor_to_expression(select.columns, select.r#where);
+pub fn select(db: &impl Storage, select: Select) -> Result<ResultSet, EngineError> {
+    let (schema, rows) = select_iter(db, select)?;
+    Ok(ResultSet {
+        rows: rows.collect::<Result<_, _>>()?,
+        schema,
+    })
+}
+
+/// typecheck the query and then return its schema, and the rows lazily as they
+/// are read from storage, so callers can stream results or stop early
+pub fn select_iter(
+    db: &impl Storage,
+    select: Select,
+) -> Result<
+    (
+        Schema,
+        impl Iterator<Item = Result<Vec<ScalarValue>, EngineError>> + '_,
+    ),
+    EngineError,
+> {
+    let (table, schema) = typecheck_against_table(lookup_table(db, &select.table)?, &select)?;
+    let rows = scan_table(db, &select.table);
+    Ok((
+        schema,
+        without_constructors(filter_rows(table, select, rows)),
+    ))
+}
+
+/// like `select_iter`, but also returning the table, and the constructor each
+/// row was stored with
+pub(crate) fn select_with_constructors(
+    db: &impl Storage,
+    select: Select,
+) -> Result<
+    (
+        Table,
+        Schema,
+        impl Iterator<Item = Result<ConstructorRow, EngineError>> + '_,
+    ),
+    EngineError,
+> {
+    let (table, schema) = typecheck_against_table(lookup_table(db, &select.table)?, &select)?;
+    let rows = scan_table(db, &select.table);
+    Ok((table.clone(), schema, filter_rows(table, select, rows)))
+}
+
+pub(crate) fn select_iter_in<'a>(
+    tx: &'a Transaction<impl Storage>,
+    select: Select,
+) -> Result<
+    (
+        Schema,
+        impl Iterator<Item = Result<Vec<ScalarValue>, EngineError>> + 'a,
+    ),
+    EngineError,
+> {
+    let (table, schema) = typecheck_against_table(lookup_table_in(tx, &select.table)?, &select)?;
+    let rows = scan_table_in(tx, &select.table);
+    Ok((
+        schema,
+        without_constructors(filter_rows(table, select, rows)),
+    ))
+}
 
-    for (index, item) in iter.enumerate() {
-        let (key, value) = item.unwrap();
+fn typecheck_against_table(
+    table: Option<Table>,
+    select: &Select,
+) -> Result<(Table, Schema), EngineError> {
+    let table = match table {
+        Some(table) => Ok(table),
+        None => Err(SelectError::TableNotFound(select.table.clone())),
+    }?;
 
-        // prefix_iterator chucks in things we don't want, filter them out
-        if !matches_prefix(&prefix, &key) {
-            continue;
-        }
+    let mut tables = BTreeMap::new();
+    tables.insert(select.table.clone(), table);
 
-        let val_string = std::str::from_utf8(&value).unwrap();
-        let json = serde_json::Value::from_str(val_string).unwrap();
+    let schema = typecheck_select(&tables, select).map_err(SelectError::TypeError)?;
 
-        if is_true(&apply_expression(&json, &expression)) {
-            let json_object = json.as_object().unwrap();
-            // collect only the columns we care about
-            let mut output = serde_json::Map::new();
+    Ok((tables.remove(&select.table).unwrap(), schema))
+}
 
-            // only the columns we like
-            for column in &columns {
-                // if we can't find the value, return `null`
-                // the typechecker should have worked out if this should happen or not
-                let item = json_object
-                    .get(&column.to_string())
-                    .cloned()
-                    .unwrap_or(Value::Null);
-                output.insert(column.to_string(), item);
-            }
+fn without_constructors<'a>(
+    rows: impl Iterator<Item = Result<ConstructorRow, EngineError>> + 'a,
+) -> impl Iterator<Item = Result<Vec<ScalarValue>, EngineError>> + 'a {
+    rows.map(|row| row.map(|(_, values)| values))
+}
 
-            let json_value = serde_json::Value::Object(output);
+fn filter_rows<'a>(
+    table: Table,
+    select: Select,
+    rows: impl Iterator<Item = Result<(Vec<u8>, Vec<u8>), EngineError>> + 'a,
+) -> impl Iterator<Item = Result<ConstructorRow, EngineError>> + 'a {
+    // if we are using a constructor to match, we'll skip rows from the others
+    let (constructor, columns) = split_select_columns(select.columns);
+    let r#where = select.r#where;
+
+    rows.filter_map(move |item| {
+        item.and_then(|(key, value)| {
+            select_row(
+                &table,
+                constructor.as_ref(),
+                &columns,
+                &r#where,
+                &key,
+                &value,
+            )
+        })
+        .transpose()
+    })
+}
 
-            results.push((index + 1, json_value));
-        }
+/// the columns we want from this row, if it matches the query
+fn select_row(
+    table: &Table,
+    constructor: Option<&Constructor>,
+    columns: &[ColumnName],
+    r#where: &Expression,
+    key: &[u8],
+    value: &[u8],
+) -> Result<Option<ConstructorRow>, EngineError> {
+    let row = read_row(table, key, value)?;
+
+    let constructor_matches = constructor.is_none() || row.constructor.as_ref() == constructor;
+
+    if !(constructor_matches && is_true(&apply_expression(&row.values, r#where))) {
+        return Ok(None);
     }
-    Ok(results)
+
+    // only the columns we like, in the order they were asked for.
+    // if we can't find the value it is `null`, and the typechecker should have
+    // worked out if this should happen or not
+    let values = columns
+        .iter()
+        .map(|column| row.values.get(column).cloned().unwrap_or(ScalarValue::Null))
+        .collect();
+    Ok(Some((row.constructor, values)))
 }
 
 #[cfg(test)]
 mod testing {
-    use super::select;
+    use super::{select, select_iter, ResultSet};
     use crate::data::insert_table;
+    use crate::storage::{MemoryStorage, Storage};
+    use crate::EngineError;
     use engine_core::{
-        ColumnName, Constructor, Insert, InsertValue, ScalarValue, SelectError, TableName,
-        TypeError,
+        ColumnName, Constructor, Insert, InsertValue, Location, OnConflict, ScalarType,
+        ScalarValue, SelectError, TableName, Type, TypeError, TypeErrors,
     };
-    use rocksdb::{Options, DB};
     use std::collections::BTreeMap;
 
-    fn insert_test_data(db: &DB) -> anyhow::Result<()> {
+    fn insert_test_data(db: &MemoryStorage) -> anyhow::Result<()> {
         let _ = insert_user_data(db);
         insert_pet_data(db)
     }
 
-    fn insert_pet_data(db: &DB) -> anyhow::Result<()> {
+    fn insert_pet_data(db: &MemoryStorage) -> anyhow::Result<()> {
         let (_,table_sql) = engine_core::parse_table("type Pet { Cat { age: Int, name: String }, Dog { age: Int, name: String, likes_stick: Bool } }").expect("parse_table");
 
-        insert_table(db, &table_sql);
+        insert_table(db, &table_sql)?;
 
         let mut cat_row = BTreeMap::new();
         cat_row.insert(ColumnName("age".to_string()), ScalarValue::Int(27));
@@ -92,11 +215,13 @@ mod testing {
             db,
             &Insert {
                 table: TableName("Pet".to_string()),
-                key: 1,
+                key: Some(1),
                 value: InsertValue::Multiple {
                     constructor: Constructor("Cat".into()),
                     values: cat_row,
                 },
+                on_conflict: OnConflict::Fail,
+                returning_key: false,
             },
         )?;
 
@@ -115,23 +240,25 @@ mod testing {
             db,
             &Insert {
                 table: TableName("Pet".to_string()),
-                key: 2,
+                key: Some(2),
                 value: InsertValue::Multiple {
                     constructor: Constructor("Dog".into()),
                     values: dog_row,
                 },
+                on_conflict: OnConflict::Fail,
+                returning_key: false,
             },
         )?;
 
         Ok(())
     }
 
-    fn insert_user_data(db: &DB) -> anyhow::Result<()> {
+    fn insert_user_data(db: &MemoryStorage) -> anyhow::Result<()> {
         let (_, table_sql) =
             engine_core::parse_table("type User { age: Int, nice: Bool, name: String }")
                 .expect("parse_table");
 
-        insert_table(db, &table_sql);
+        insert_table(db, &table_sql)?;
 
         // todo: parser
         // insert into user [
@@ -150,8 +277,10 @@ mod testing {
             db,
             &Insert {
                 table: TableName("User".to_string()),
-                key: 1,
+                key: Some(1),
                 value: InsertValue::Single { values: user_row_1 },
+                on_conflict: OnConflict::Fail,
+                returning_key: false,
             },
         )?;
 
@@ -167,8 +296,10 @@ mod testing {
             db,
             &Insert {
                 table: TableName("User".to_string()),
-                key: 2,
+                key: Some(2),
                 value: InsertValue::Single { values: user_row_2 },
+                on_conflict: OnConflict::Fail,
+                returning_key: false,
             },
         )?;
 
@@ -184,8 +315,10 @@ mod testing {
             db,
             &Insert {
                 table: TableName("User".to_string()),
-                key: 3,
+                key: Some(3),
                 value: InsertValue::Single { values: user_row_3 },
+                on_conflict: OnConflict::Fail,
+                returning_key: false,
             },
         )?;
 
@@ -194,176 +327,247 @@ mod testing {
 
     #[test]
     fn test_missing_table() {
-        let path = format!("./test_storage{}", rand::random::<i32>());
-        {
-            let db = DB::open_default(path.clone()).unwrap();
-            insert_test_data(&db).expect("insert test data failure");
-
-            let (_, select_sql) =
-                engine_core::parse_select("select name from Missing").expect("parse_select");
-
-            assert_eq!(
-                select(&db, select_sql),
-                Err(SelectError::TableNotFound(TableName("Missing".to_string())))
-            );
-        }
-        let _ = DB::destroy(&Options::default(), path);
+        let db = MemoryStorage::default();
+        insert_test_data(&db).expect("insert test data failure");
+
+        let (_, select_sql) =
+            engine_core::parse_select("select name from Missing").expect("parse_select");
+
+        assert_eq!(
+            select(&db, select_sql),
+            Err(EngineError::Select(SelectError::TableNotFound(TableName(
+                "Missing".to_string()
+            ))))
+        );
     }
 
     #[test]
     fn test_missing_column() {
-        let path = format!("./test_storage{}", rand::random::<i32>());
-        {
-            let db = DB::open_default(path.clone()).unwrap();
-            insert_test_data(&db).expect("insert test data failure");
-
-            let (_, select_sql) =
-                engine_core::parse_select("select missing from User").expect("parse_select");
-
-            assert_eq!(
-                select(&db, select_sql),
-                Err(SelectError::TypeError(TypeError::ColumnNotFound {
-                    column_name: ColumnName("missing".to_string()),
-                    table_name: TableName("User".to_string())
-                }))
-            );
-        }
-        let _ = DB::destroy(&Options::default(), path);
+        let db = MemoryStorage::default();
+        insert_test_data(&db).expect("insert test data failure");
+
+        let (_, select_sql) =
+            engine_core::parse_select("select missing from User").expect("parse_select");
+
+        assert_eq!(
+            select(&db, select_sql),
+            Err(EngineError::Select(SelectError::TypeError(TypeErrors(
+                vec![(
+                    Location::Column(ColumnName("missing".to_string())),
+                    TypeError::ColumnNotFound {
+                        column_name: ColumnName("missing".to_string()),
+                        table_name: TableName("User".to_string())
+                    }
+                )]
+            ))))
+        );
     }
 
     #[test]
     fn test_missing_column_in_where() {
-        let path = format!("./test_storage{}", rand::random::<i32>());
-        {
-            let db = DB::open_default(path.clone()).unwrap();
-            insert_test_data(&db).expect("insert test data failure");
-
-            let (_, select_sql) =
-                engine_core::parse_select("select name from User where missing = true")
-                    .expect("parse_select");
-
-            assert_eq!(
-                select(&db, select_sql),
-                Err(SelectError::TypeError(TypeError::ColumnNotFound {
-                    column_name: ColumnName("missing".to_string()),
-                    table_name: TableName("User".to_string())
-                }))
-            );
-        }
-        let _ = DB::destroy(&Options::default(), path);
+        let db = MemoryStorage::default();
+        insert_test_data(&db).expect("insert test data failure");
+
+        let (_, select_sql) =
+            engine_core::parse_select("select name from User where missing = true")
+                .expect("parse_select");
+
+        assert_eq!(
+            select(&db, select_sql),
+            Err(EngineError::Select(SelectError::TypeError(TypeErrors(
+                vec![(
+                    Location::Where(ColumnName("missing".to_string())),
+                    TypeError::ColumnNotFound {
+                        column_name: ColumnName("missing".to_string()),
+                        table_name: TableName("User".to_string())
+                    }
+                )]
+            ))))
+        );
     }
 
     #[test]
     fn test_get_users() {
-        let path = format!("./test_storage{}", rand::random::<i32>());
-        {
-            let db = DB::open_default(path.clone()).unwrap();
-            insert_test_data(&db).expect("insert test data failure");
-
-            let expected = vec![
-                (1, serde_json::from_str("{\"name\":\"Egg\"}").unwrap()),
-                (2, serde_json::from_str("{\"name\":\"Horse\"}").unwrap()),
-                (3, serde_json::from_str("{\"name\":\"Log\"}").unwrap()),
-            ];
-
-            let (_, select_sql) =
-                engine_core::parse_select("select name from User").expect("parse_select");
-
-            assert_eq!(select(&db, select_sql), Ok(expected));
-        }
-        let _ = DB::destroy(&Options::default(), path);
+        let db = MemoryStorage::default();
+        insert_test_data(&db).expect("insert test data failure");
+
+        let expected = ResultSet {
+            schema: vec![(
+                ColumnName("name".to_string()),
+                Type::ScalarType(ScalarType::String),
+            )],
+            rows: vec![
+                vec![ScalarValue::String("Egg".to_string())],
+                vec![ScalarValue::String("Horse".to_string())],
+                vec![ScalarValue::String("Log".to_string())],
+            ],
+        };
+
+        let (_, select_sql) =
+            engine_core::parse_select("select name from User").expect("parse_select");
+
+        assert_eq!(select(&db, select_sql), Ok(expected));
     }
 
     #[test]
     fn test_get_users_where() {
-        let path = format!("./test_storage{}", rand::random::<i32>());
-        {
-            let db = DB::open_default(path.clone()).unwrap();
-            insert_test_data(&db).expect("insert test data failure");
+        let db = MemoryStorage::default();
+        insert_test_data(&db).expect("insert test data failure");
 
-            let expected = vec![(2, serde_json::from_str("{\"name\":\"Horse\"}").unwrap())];
+        let expected = vec![vec![ScalarValue::String("Horse".to_string())]];
 
-            let (_, select_sql) =
-                engine_core::parse_select("select name from User where nice = true && age = 100")
-                    .expect("parse_select");
+        let (_, select_sql) =
+            engine_core::parse_select("select name from User where nice = true && age = 100")
+                .expect("parse_select");
 
-            assert_eq!(select(&db, select_sql), Ok(expected));
-        }
-        let _ = DB::destroy(&Options::default(), path);
+        assert_eq!(
+            select(&db, select_sql).map(|result| result.rows),
+            Ok(expected)
+        );
     }
 
     #[test]
     fn test_get_cats() {
-        let path = format!("./test_storage{}", rand::random::<i32>());
-        {
-            let db = DB::open_default(path.clone()).unwrap();
-            insert_test_data(&db).expect("insert test data failure");
+        let db = MemoryStorage::default();
+        insert_test_data(&db).expect("insert test data failure");
 
-            let expected = vec![(
-                1,
-                serde_json::from_str("{\"age\":27,\"name\":\"Mr Cat\"}").unwrap(),
-            )];
+        let expected = vec![vec![
+            ScalarValue::Int(27),
+            ScalarValue::String("Mr Cat".to_string()),
+        ]];
 
-            let (_, select_sql) = engine_core::parse_select("select Cat { age, name } from Pet")
-                .expect("parse_select");
+        let (_, select_sql) =
+            engine_core::parse_select("select Cat { age, name } from Pet").expect("parse_select");
 
-            assert_eq!(select(&db, select_sql), Ok(expected));
-        }
-        let _ = DB::destroy(&Options::default(), path);
+        assert_eq!(
+            select(&db, select_sql).map(|result| result.rows),
+            Ok(expected)
+        );
     }
 
     #[test]
     fn test_get_pets() {
-        let path = format!("./test_storage{}", rand::random::<i32>());
-        {
-            let db = DB::open_default(path.clone()).unwrap();
-            insert_test_data(&db).expect("insert test data failure");
+        let db = MemoryStorage::default();
+        insert_test_data(&db).expect("insert test data failure");
+
+        let expected = vec![
+            vec![
+                ScalarValue::Int(27),
+                ScalarValue::String("Mr Cat".to_string()),
+            ],
+            vec![
+                ScalarValue::Int(21),
+                ScalarValue::String("Mr Dog".to_string()),
+            ],
+        ];
+
+        let (_, select_sql) =
+            engine_core::parse_select("select age, name from Pet").expect("parse_select");
+
+        assert_eq!(
+            select(&db, select_sql).map(|result| result.rows),
+            Ok(expected)
+        );
+    }
 
-            let expected = vec![
+    #[test]
+    fn test_get_pets_with_nullable() {
+        let db = MemoryStorage::default();
+        insert_test_data(&db).expect("insert test data failure");
+
+        let expected = ResultSet {
+            schema: vec![
                 (
-                    1,
-                    serde_json::from_str("{\"age\":27,\"name\":\"Mr Cat\"}").unwrap(),
+                    ColumnName("age".to_string()),
+                    Type::ScalarType(ScalarType::Int),
                 ),
                 (
-                    2,
-                    serde_json::from_str("{\"age\":21,\"name\":\"Mr Dog\"}").unwrap(),
+                    ColumnName("name".to_string()),
+                    Type::ScalarType(ScalarType::String),
                 ),
-            ];
+                (
+                    ColumnName("likes_stick".to_string()),
+                    Type::Optional(Box::new(Type::ScalarType(ScalarType::Bool))),
+                ),
+            ],
+            rows: vec![
+                vec![
+                    ScalarValue::Int(27),
+                    ScalarValue::String("Mr Cat".to_string()),
+                    ScalarValue::Null,
+                ],
+                vec![
+                    ScalarValue::Int(21),
+                    ScalarValue::String("Mr Dog".to_string()),
+                    ScalarValue::Bool(true),
+                ],
+            ],
+        };
+
+        let (_, select_sql) = engine_core::parse_select("select age, name, likes_stick from Pet")
+            .expect("parse select");
+
+        let result = select(&db, select_sql).expect("select");
+        assert_eq!(
+            result.to_json(),
+            vec![
+                serde_json::json!({ "age": 27, "name": "Mr Cat", "likes_stick": null }),
+                serde_json::json!({ "age": 21, "name": "Mr Dog", "likes_stick": true }),
+            ]
+        );
+        assert_eq!(result, expected);
+    }
 
-            let (_, select_sql) =
-                engine_core::parse_select("select age, name from Pet").expect("parse_select");
+    #[test]
+    fn test_corrupt_row() {
+        let db = MemoryStorage::default();
+        insert_test_data(&db).expect("insert test data failure");
+
+        db.put(b"data_User_4", &[1, 0, 27]).unwrap();
 
-            assert_eq!(select(&db, select_sql), Ok(expected));
-        }
-        let _ = DB::destroy(&Options::default(), path);
+        let (_, select_sql) =
+            engine_core::parse_select("select name from User").expect("parse_select");
+
+        assert!(matches!(
+            select(&db, select_sql),
+            Err(EngineError::CorruptRow { key, .. }) if key == "data_User_4"
+        ));
     }
 
     #[test]
-    fn test_get_pets_with_nullable() {
-        let path = format!("./test_storage{}", rand::random::<i32>());
-        {
-            let db = DB::open_default(path.clone()).unwrap();
-            insert_test_data(&db).expect("insert test data failure");
+    fn test_select_iter_stops_early() {
+        let db = MemoryStorage::default();
+        insert_test_data(&db).expect("insert test data failure");
 
-            let expected = vec![
-                (
-                    1,
-                    serde_json::from_str("{\"age\":27,\"name\":\"Mr Cat\", \"likes_stick\":null}")
-                        .unwrap(),
-                ),
-                (
-                    2,
-                    serde_json::from_str("{\"age\":21,\"name\":\"Mr Dog\", \"likes_stick\": true}")
-                        .unwrap(),
-                ),
-            ];
+        // this row is never reached, so doesn't cause an error
+        db.put(b"data_User_4", &[1, 0, 27]).unwrap();
+
+        let (_, select_sql) =
+            engine_core::parse_select("select name from User").expect("parse_select");
+
+        let (_, rows) = select_iter(&db, select_sql).expect("select_iter");
+        let first_two: Vec<_> = rows.take(2).collect();
+
+        assert_eq!(
+            first_two,
+            vec![
+                Ok(vec![ScalarValue::String("Egg".to_string())]),
+                Ok(vec![ScalarValue::String("Horse".to_string())]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_iter_typechecks_up_front() {
+        let db = MemoryStorage::default();
+        insert_test_data(&db).expect("insert test data failure");
 
-            let (_, select_sql) =
-                engine_core::parse_select("select age, name, likes_stick from Pet")
-                    .expect("parse select");
+        let (_, select_sql) =
+            engine_core::parse_select("select missing from User").expect("parse_select");
 
-            assert_eq!(select(&db, select_sql), Ok(expected));
-        }
-        let _ = DB::destroy(&Options::default(), path);
+        assert!(matches!(
+            select_iter(&db, select_sql),
+            Err(EngineError::Select(SelectError::TypeError(_)))
+        ));
     }
 }
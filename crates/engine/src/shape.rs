@@ -0,0 +1,416 @@
+//! the shape of row a Rust type can be deserialized from, found by
+//! deserializing one from a tracer that writes down everything it is asked for
+//!
+//! serde has no way to ask a type what it wants without building one, so each
+//! field is given `0`, `false` or `""`. a type that turns those down, like
+//! `NonZeroI32` or anything that checks its value in `Deserialize`, can't be
+//! traced and is reported as unsupported, even if every real row would fit it
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use std::fmt::{self, Display};
+
+/// what a field asks to be deserialized from
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum FieldShape {
+    Bool,
+    Int,
+    String,
+    Option(Box<FieldShape>),
+    /// takes whatever it is given, like `serde_json::Value`
+    Any,
+    /// something no column holds, like a float
+    Other(&'static str),
+}
+
+impl Display for FieldShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldShape::Bool => write!(f, "a bool"),
+            FieldShape::Int => write!(f, "an integer"),
+            FieldShape::String => write!(f, "a string"),
+            FieldShape::Option(inner) => write!(f, "an `Option` of {inner}"),
+            FieldShape::Any => write!(f, "anything"),
+            FieldShape::Other(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+pub(crate) type Fields = Vec<(&'static str, FieldShape)>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Shape {
+    /// a struct with named fields
+    Struct(Fields),
+    /// an enum with a variant of named fields for each constructor
+    Enum(Vec<(&'static str, Fields)>),
+    /// takes a whole row of whatever columns, like `serde_json::Value`
+    Any,
+}
+
+/// the shape of `T`, or why rows cannot be read into one
+pub(crate) fn shape<T: DeserializeOwned>() -> Result<Shape, String> {
+    match trace::<T>(0)? {
+        Found::Any => Ok(Shape::Any),
+        Found::Struct(fields) => Ok(Shape::Struct(fields)),
+        Found::Variant { variants, fields } => {
+            // each trace can only go down one variant, so try each in turn
+            let mut traced = vec![(variants[0], fields)];
+            for (index, variant) in variants.iter().enumerate().skip(1) {
+                let Found::Variant { fields, .. } = trace::<T>(index)? else {
+                    return Err(format!("variant {variant} could not be traced"));
+                };
+                traced.push((variant, fields));
+            }
+            Ok(Shape::Enum(traced))
+        }
+    }
+}
+
+fn trace<T: DeserializeOwned>(variant: usize) -> Result<Found, String> {
+    let mut found = None;
+    T::deserialize(Tracer {
+        variant,
+        found: &mut found,
+    })
+    .map_err(|error| error.0)?;
+    found.ok_or_else(|| "it is not a struct or an enum".to_string())
+}
+
+#[derive(Debug)]
+struct TraceError(String);
+
+impl Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+impl de::Error for TraceError {
+    fn custom<T: Display>(message: T) -> Self {
+        TraceError(message.to_string())
+    }
+}
+
+fn unsupported<T>(field: &str) -> Result<T, TraceError> {
+    Err(TraceError(format!("{field} cannot be read from a column")))
+}
+
+enum Found {
+    Struct(Fields),
+    Variant {
+        variants: &'static [&'static str],
+        fields: Fields,
+    },
+    Any,
+}
+
+// the type as a whole, going down the variant numbered `variant` if it is an enum
+struct Tracer<'a> {
+    variant: usize,
+    found: &'a mut Option<Found>,
+}
+
+impl<'de> de::Deserializer<'de> for Tracer<'_> {
+    type Error = TraceError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        *self.found = Some(Found::Any);
+        visitor.visit_map(FieldsTracer {
+            names: &[],
+            fields: &mut vec![],
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        let mut traced = vec![];
+        let value = visitor.visit_map(FieldsTracer {
+            names: fields,
+            fields: &mut traced,
+        })?;
+        *self.found = Some(Found::Struct(traced));
+        Ok(value)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        let Some(name) = variants.get(self.variant) else {
+            return Err(TraceError("an enum with no variants".to_string()));
+        };
+        let mut traced = vec![];
+        let value = visitor.visit_enum(VariantTracer {
+            name,
+            fields: &mut traced,
+        })?;
+        *self.found = Some(Found::Variant {
+            variants,
+            fields: traced,
+        });
+        Ok(value)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+        byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map
+        identifier ignored_any
+    }
+}
+
+// the named fields of a struct or a variant
+struct FieldsTracer<'a> {
+    names: &'static [&'static str],
+    fields: &'a mut Fields,
+}
+
+impl<'de> MapAccess<'de> for FieldsTracer<'_> {
+    type Error = TraceError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, TraceError> {
+        match self.names.get(self.fields.len()) {
+            Some(name) => seed.deserialize((*name).into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, TraceError> {
+        let name = self.names[self.fields.len()];
+        let mut shape = FieldShape::Any;
+        let value = seed
+            .deserialize(FieldTracer { shape: &mut shape })
+            .map_err(|error| TraceError(format!("field {name}: {error}")))?;
+        self.fields.push((name, shape));
+        Ok(value)
+    }
+}
+
+// a single field, which is given a placeholder of whatever it asks for
+struct FieldTracer<'a> {
+    shape: &'a mut FieldShape,
+}
+
+macro_rules! trace_int {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+                *self.shape = FieldShape::Int;
+                visitor.visit_u8(0)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for FieldTracer<'_> {
+    type Error = TraceError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        *self.shape = FieldShape::Any;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        *self.shape = FieldShape::Bool;
+        visitor.visit_bool(false)
+    }
+
+    trace_int! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        *self.shape = FieldShape::Other("a float");
+        visitor.visit_f64(0.0)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        *self.shape = FieldShape::Other("a char");
+        visitor.visit_char(' ')
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        *self.shape = FieldShape::String;
+        visitor.visit_str("")
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        let mut inner = FieldShape::Any;
+        let value = visitor.visit_some(FieldTracer { shape: &mut inner })?;
+        *self.shape = FieldShape::Option(Box::new(inner));
+        Ok(value)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, TraceError> {
+        unsupported("bytes")
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, TraceError> {
+        unsupported("bytes")
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, TraceError> {
+        unsupported("`()`")
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        unsupported(name)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, TraceError> {
+        unsupported("a sequence")
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        unsupported("a tuple")
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        unsupported(name)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, TraceError> {
+        unsupported("a map")
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        unsupported(name)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        unsupported(name)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, TraceError> {
+        unsupported("an identifier")
+    }
+}
+
+// the variant being traced, which takes its fields from the struct inside it
+// or from its own named fields
+struct VariantTracer<'a> {
+    name: &'static str,
+    fields: &'a mut Fields,
+}
+
+impl<'de> EnumAccess<'de> for VariantTracer<'_> {
+    type Error = TraceError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), TraceError> {
+        let value = seed.deserialize(self.name.into_deserializer())?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantTracer<'_> {
+    type Error = TraceError;
+
+    fn unit_variant(self) -> Result<(), TraceError> {
+        Err(TraceError(format!("variant {} has no fields", self.name)))
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, TraceError> {
+        let mut found = None;
+        let value = seed.deserialize(Tracer {
+            variant: 0,
+            found: &mut found,
+        })?;
+        let Some(Found::Struct(fields)) = found else {
+            return Err(TraceError(format!(
+                "variant {} has to hold a struct",
+                self.name
+            )));
+        };
+        *self.fields = fields;
+        Ok(value)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        Err(TraceError(format!(
+            "variant {} has to have named fields",
+            self.name
+        )))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        visitor.visit_map(FieldsTracer {
+            names: fields,
+            fields: self.fields,
+        })
+    }
+}
@@ -0,0 +1,176 @@
+//! where the bytes actually live
+//!
+//! Everything else in the engine talks to a `Storage` rather than `RocksDB`
+//! directly, so the same code can run on disk or entirely in memory.
+use super::helpers::matches_prefix;
+use crate::EngineError;
+use std::collections::BTreeMap;
+use std::sync::{PoisonError, RwLock};
+
+/// a set of changes to apply together, `None` means the key should be deleted
+pub type Changes = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// every stored key and value starting with a prefix, in key order
+pub type Scan<'a> = Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>), EngineError>> + 'a>;
+
+pub trait Storage {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, EngineError>;
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), EngineError>;
+
+    fn delete(&self, key: &[u8]) -> Result<(), EngineError>;
+
+    /// every key starting with `prefix`, in order
+    fn scan(&self, prefix: &str) -> Scan<'_>;
+
+    /// apply all of the changes, or none of them
+    fn write(&self, changes: Changes) -> Result<(), EngineError>;
+}
+
+#[cfg(feature = "rocksdb")]
+impl Storage for rocksdb::DB {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, EngineError> {
+        Ok(rocksdb::DB::get(self, key)?)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), EngineError> {
+        Ok(rocksdb::DB::put(self, key, value)?)
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<(), EngineError> {
+        Ok(rocksdb::DB::delete(self, key)?)
+    }
+
+    fn scan(&self, prefix: &str) -> Scan<'_> {
+        let stored_prefix = prefix.to_string();
+        Box::new(
+            self.prefix_iterator(prefix)
+                .map(|item| {
+                    item.map(|(key, value)| (key.into_vec(), value.into_vec()))
+                        .map_err(EngineError::from)
+                })
+                // prefix_iterator chucks in things we don't want, stop once we reach them
+                .take_while(move |item| {
+                    item.as_ref()
+                        .map_or(true, |(key, _)| matches_prefix(&stored_prefix, key))
+                }),
+        )
+    }
+
+    fn write(&self, changes: Changes) -> Result<(), EngineError> {
+        let mut batch = rocksdb::WriteBatch::default();
+        for (key, value) in changes {
+            match value {
+                Some(value) => batch.put(key, value),
+                None => batch.delete(key),
+            }
+        }
+        Ok(rocksdb::DB::write(self, batch)?)
+    }
+}
+
+/// keeps everything in a `BTreeMap`, for tests or when nothing needs to outlive
+/// the process
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    rows: RwLock<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, EngineError> {
+        let rows = self.rows.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(rows.get(key).cloned())
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), EngineError> {
+        let mut rows = self.rows.write().unwrap_or_else(PoisonError::into_inner);
+        rows.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<(), EngineError> {
+        let mut rows = self.rows.write().unwrap_or_else(PoisonError::into_inner);
+        rows.remove(key);
+        Ok(())
+    }
+
+    /// copies the matching rows up front, so the lock isn't held while the
+    /// caller works through them
+    fn scan(&self, prefix: &str) -> Scan<'_> {
+        let rows = self.rows.read().unwrap_or_else(PoisonError::into_inner);
+        let matching: Vec<_> = rows
+            .range(prefix.as_bytes().to_vec()..)
+            .take_while(|(key, _)| matches_prefix(prefix, key))
+            .map(|(key, value)| Ok((key.clone(), value.clone())))
+            .collect();
+        Box::new(matching.into_iter())
+    }
+
+    fn write(&self, changes: Changes) -> Result<(), EngineError> {
+        let mut rows = self.rows.write().unwrap_or_else(PoisonError::into_inner);
+        for (key, value) in changes {
+            match value {
+                Some(value) => rows.insert(key, value),
+                None => rows.remove(&key),
+            };
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::{Changes, MemoryStorage, Storage};
+
+    fn scan_keys(storage: &impl Storage, prefix: &str) -> Vec<Vec<u8>> {
+        storage
+            .scan(prefix)
+            .map(|item| item.expect("scan").0)
+            .collect()
+    }
+
+    fn check_storage(storage: &impl Storage) {
+        storage.put(b"data_Pet_1", b"cat").expect("put");
+        storage.put(b"data_User_1", b"egg").expect("put");
+        storage.put(b"data_User_2", b"horse").expect("put");
+        storage.put(b"table_User", b"{}").expect("put");
+
+        assert_eq!(storage.get(b"data_User_2"), Ok(Some(b"horse".to_vec())));
+        assert_eq!(
+            scan_keys(storage, "data_User_"),
+            vec![b"data_User_1".to_vec(), b"data_User_2".to_vec()]
+        );
+
+        let mut changes = Changes::new();
+        changes.insert(b"data_User_1".to_vec(), None);
+        changes.insert(b"data_User_3".to_vec(), Some(b"dog".to_vec()));
+        storage.write(changes).expect("write");
+
+        assert_eq!(storage.get(b"data_User_1"), Ok(None));
+        assert_eq!(
+            scan_keys(storage, "data_User_"),
+            vec![b"data_User_2".to_vec(), b"data_User_3".to_vec()]
+        );
+
+        storage.delete(b"data_Pet_1").expect("delete");
+        assert_eq!(scan_keys(storage, "data_Pet_"), Vec::<Vec<u8>>::new());
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        check_storage(&MemoryStorage::default());
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[test]
+    fn test_rocksdb_storage() {
+        use rocksdb::{Options, DB};
+
+        let path = format!("./test_storage{}", rand::random::<i32>());
+        {
+            let db = DB::open_default(path.clone()).unwrap();
+            check_storage(&db);
+        }
+        let _ = DB::destroy(&Options::default(), path);
+    }
+}
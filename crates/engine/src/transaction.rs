@@ -0,0 +1,481 @@
+//! optimistic transactions on top of any `Storage`
+//!
+//! Writes are buffered in the `Transaction` and applied in a single `WriteBatch` on
+//! `commit`. Every key we read or write remembers the value it had when we first
+//! saw it, and if any of those have changed by the time we commit we bail out with
+//! a conflict rather than clobbering another writer. Tables scanned by `update`
+//! and `delete` are watched as a whole, so a row another writer adds to them is a
+//! conflict too.
+use super::helpers::matches_prefix;
+use super::storage::Storage;
+use crate::data::scan_table;
+use crate::select::{ResultSet, Schema};
+use crate::EngineError;
+use engine_core::{AlterTable, Delete, Insert, ScalarValue, Select, Table, TableName, Update};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter::Peekable;
+use std::sync::{Mutex, PoisonError};
+
+/// held while validating and writing a commit, so checks and writes can't interleave
+static COMMIT_LOCK: Mutex<()> = Mutex::new(());
+
+pub struct Transaction<'a, S> {
+    db: &'a S,
+    /// the stored value of every key we have touched, as it was when we first saw it
+    seen: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+    /// uncommitted writes, `None` means the key has been deleted
+    pending: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+    /// tables that must not gain any rows we haven't seen
+    watched: BTreeSet<TableName>,
+}
+
+pub fn begin<S: Storage>(db: &S) -> Transaction<'_, S> {
+    Transaction {
+        db,
+        seen: BTreeMap::new(),
+        pending: BTreeMap::new(),
+        watched: BTreeSet::new(),
+    }
+}
+
+/// run `f` in its own transaction and commit it, starting again if another
+/// writer got there first
+pub(crate) fn autocommit<S: Storage, T>(
+    db: &S,
+    f: impl Fn(&mut Transaction<S>) -> Result<T, EngineError>,
+) -> Result<T, EngineError> {
+    loop {
+        let mut tx = begin(db);
+        let result = f(&mut tx)?;
+        match tx.commit() {
+            // someone else got there first, go again
+            Err(EngineError::TransactionConflict { .. }) => {}
+            Err(error) => return Err(error),
+            Ok(()) => return Ok(result),
+        }
+    }
+}
+
+impl<S: Storage> Transaction<'_, S> {
+    pub fn insert(&mut self, insert: &Insert) -> Result<i32, EngineError> {
+        crate::insert::insert_in(self, insert)
+    }
+
+    pub fn select(&self, select: Select) -> Result<ResultSet, EngineError> {
+        let (schema, rows) = self.select_iter(select)?;
+        Ok(ResultSet {
+            rows: rows.collect::<Result<_, _>>()?,
+            schema,
+        })
+    }
+
+    pub fn select_iter(
+        &self,
+        select: Select,
+    ) -> Result<
+        (
+            Schema,
+            impl Iterator<Item = Result<Vec<ScalarValue>, EngineError>> + '_,
+        ),
+        EngineError,
+    > {
+        crate::select::select_iter_in(self, select)
+    }
+
+    pub fn delete(&mut self, delete: &Delete) -> Result<usize, EngineError> {
+        crate::delete::delete_in(self, delete)
+    }
+
+    pub fn update(&mut self, update: &Update) -> Result<usize, EngineError> {
+        crate::update::update_in(self, update)
+    }
+
+    pub fn alter_table(&mut self, alter: &AlterTable) -> Result<usize, EngineError> {
+        crate::alter::alter_table_in(self, alter)
+    }
+
+    pub fn drop_table(&mut self, table_name: &TableName) -> Result<usize, EngineError> {
+        crate::catalog::drop_table_in(self, table_name)
+    }
+
+    pub fn insert_table(&mut self, table: &Table) -> Result<(), EngineError> {
+        crate::data::insert_table_in(self, table)
+    }
+
+    /// write everything in one batch, unless something we depend on has changed
+    pub fn commit(self) -> Result<(), EngineError> {
+        let _guard = COMMIT_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let conflict = |key: &[u8]| EngineError::TransactionConflict {
+            key: String::from_utf8_lossy(key).to_string(),
+        };
+
+        for (key, seen) in &self.seen {
+            if self.db.get(key)? != *seen {
+                return Err(conflict(key));
+            }
+        }
+
+        for table_name in &self.watched {
+            for item in scan_table(self.db, table_name) {
+                let (key, _) = item?;
+                if !self.seen.contains_key(&key) {
+                    return Err(conflict(&key));
+                }
+            }
+        }
+
+        self.db.write(self.pending)
+    }
+
+    /// throw away everything we've written
+    pub fn rollback(self) {}
+
+    /// the value as this transaction sees it, without checking it on commit
+    pub(crate) fn read(&self, key: &[u8]) -> Result<Option<Vec<u8>>, EngineError> {
+        match self.pending.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => self.db.get(key),
+        }
+    }
+
+    /// read the stored value, remembering it so we can spot changes on commit
+    fn touch(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, EngineError> {
+        if let Some(seen) = self.seen.get(key) {
+            return Ok(seen.clone());
+        }
+        let stored = self.db.get(key)?;
+        self.seen.insert(key.to_vec(), stored.clone());
+        Ok(stored)
+    }
+
+    /// remember every stored row of the table as it is now, so that commit fails
+    /// if any of them change or another writer adds one
+    pub(crate) fn watch_table(&mut self, table_name: &TableName) -> Result<(), EngineError> {
+        for item in scan_table(self.db, table_name) {
+            let (key, value) = item?;
+            self.seen.entry(key).or_insert(Some(value));
+        }
+        self.watched.insert(table_name.clone());
+        Ok(())
+    }
+
+    pub(crate) fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, EngineError> {
+        match self.pending.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => self.touch(key),
+        }
+    }
+
+    pub(crate) fn put(&mut self, key: &[u8], value: Vec<u8>) -> Result<(), EngineError> {
+        self.touch(key)?;
+        self.pending.insert(key.to_vec(), Some(value));
+        Ok(())
+    }
+
+    pub(crate) fn delete_key(&mut self, key: &[u8]) -> Result<(), EngineError> {
+        self.touch(key)?;
+        self.pending.insert(key.to_vec(), None);
+        Ok(())
+    }
+
+    /// every row starting with `prefix`, including our own uncommitted writes.
+    /// rows read this way are not checked for conflicts on commit, unless the
+    /// table has been watched with `watch_table`
+    pub(crate) fn scan(
+        &self,
+        prefix: &str,
+    ) -> impl Iterator<Item = Result<(Vec<u8>, Vec<u8>), EngineError>> + '_ {
+        let stored = self.db.scan(prefix);
+
+        let pending_prefix = prefix.to_string();
+        let pending = self
+            .pending
+            .range(prefix.as_bytes().to_vec()..)
+            .take_while(move |(key, _)| matches_prefix(&pending_prefix, key));
+
+        Overlay {
+            stored: stored.peekable(),
+            pending: pending.peekable(),
+        }
+    }
+}
+
+/// merges two sorted streams of rows, preferring pending writes over stored values
+struct Overlay<S: Iterator, P: Iterator> {
+    stored: Peekable<S>,
+    pending: Peekable<P>,
+}
+
+impl<'p, S, P> Iterator for Overlay<S, P>
+where
+    S: Iterator<Item = Result<(Vec<u8>, Vec<u8>), EngineError>>,
+    P: Iterator<Item = (&'p Vec<u8>, &'p Option<Vec<u8>>)>,
+{
+    type Item = Result<(Vec<u8>, Vec<u8>), EngineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let order = match (self.stored.peek(), self.pending.peek()) {
+                (None, None) => return None,
+                (Some(_), None) | (Some(Err(_)), Some(_)) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(Ok((stored_key, _))), Some((pending_key, _))) => stored_key.cmp(pending_key),
+            };
+
+            match order {
+                Ordering::Less => return self.stored.next(),
+                // our write replaces the stored row
+                Ordering::Equal => {
+                    self.stored.next();
+                }
+                Ordering::Greater => {}
+            }
+
+            if let Some((key, Some(value))) = self.pending.next() {
+                return Some(Ok((key.clone(), value.clone())));
+            }
+            // otherwise it's a delete, skip it
+        }
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::begin;
+    use crate::data::insert_table;
+    use crate::storage::{MemoryStorage, Storage};
+    use crate::EngineError;
+    use engine_core::{ColumnName, Insert, InsertValue, OnConflict, ScalarValue, TableName};
+    use std::collections::BTreeMap;
+
+    fn user_insert(key: i32, age: i32) -> Insert {
+        let mut values = BTreeMap::new();
+        values.insert(ColumnName("age".to_string()), ScalarValue::Int(age));
+
+        Insert {
+            table: TableName("User".to_string()),
+            key: Some(key),
+            value: InsertValue::Single { values },
+            on_conflict: OnConflict::Fail,
+            returning_key: false,
+        }
+    }
+
+    fn select_ages(db: &MemoryStorage) -> Vec<Vec<ScalarValue>> {
+        let (_, select_sql) =
+            engine_core::parse_select("select age from User").expect("parse_select");
+        crate::select::select(db, select_sql).expect("select").rows
+    }
+
+    #[test]
+    fn test_sees_own_writes() {
+        let db = MemoryStorage::default();
+        let (_, table) = engine_core::parse_table("type User { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+        crate::insert::insert(&db, &user_insert(1, 27)).expect("insert");
+
+        let mut tx = begin(&db);
+        tx.insert(&user_insert(2, 100)).expect("insert");
+        let (_, delete_sql) =
+            engine_core::parse_delete("delete from User where age = 27").expect("parse_delete");
+        assert_eq!(tx.delete(&delete_sql), Ok(1));
+
+        let (_, select_sql) =
+            engine_core::parse_select("select age from User").expect("parse_select");
+        assert_eq!(
+            tx.select(select_sql).map(|result| result.rows),
+            Ok(vec![vec![ScalarValue::Int(100)]])
+        );
+
+        // nothing has happened outside the transaction yet
+        assert_eq!(select_ages(&db), vec![vec![ScalarValue::Int(27)]]);
+
+        tx.commit().expect("commit");
+
+        assert_eq!(select_ages(&db), vec![vec![ScalarValue::Int(100)]]);
+    }
+
+    #[test]
+    fn test_rollback() {
+        let db = MemoryStorage::default();
+        let (_, table) = engine_core::parse_table("type User { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+
+        let mut tx = begin(&db);
+        tx.insert(&user_insert(1, 27)).expect("insert");
+        tx.rollback();
+
+        assert!(select_ages(&db).is_empty());
+    }
+
+    #[test]
+    fn test_conflicting_writers() {
+        let db = MemoryStorage::default();
+        let (_, table) = engine_core::parse_table("type User { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+
+        let mut first = begin(&db);
+        let mut second = begin(&db);
+        first.insert(&user_insert(1, 27)).expect("insert");
+        second.insert(&user_insert(1, 100)).expect("insert");
+
+        first.commit().expect("commit");
+        assert_eq!(
+            second.commit(),
+            Err(EngineError::TransactionConflict {
+                key: "data_User_1".to_string()
+            })
+        );
+
+        assert_eq!(select_ages(&db), vec![vec![ScalarValue::Int(27)]]);
+    }
+
+    #[test]
+    fn test_insert_conflicts_with_a_table_change() {
+        let db = MemoryStorage::default();
+        let (_, table) = engine_core::parse_table("type User { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+
+        // the row is encoded for a table without `name`
+        let mut first = begin(&db);
+        first.insert(&user_insert(1, 27)).expect("insert");
+
+        let (_, alter_sql) =
+            engine_core::parse_alter("alter type User add column name: String default \"Egg\"")
+                .expect("parse_alter");
+        assert_eq!(crate::alter::alter_table(&db, &alter_sql), Ok(0));
+
+        assert_eq!(
+            first.commit(),
+            Err(EngineError::TransactionConflict {
+                key: "table_User".to_string()
+            })
+        );
+        assert!(select_ages(&db).is_empty());
+    }
+
+    #[test]
+    fn test_table_change_conflicts_with_a_concurrent_insert() {
+        let db = MemoryStorage::default();
+        let (_, table) =
+            engine_core::parse_table("type Pet { Cat { age: Int }, Dog { age: Int } }")
+                .expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+
+        // adding `Ant` moves `Dog` to a different constructor index
+        let mut first = begin(&db);
+        let (_, alter_sql) =
+            engine_core::parse_alter("alter type Pet add constructor Ant { age: Int }")
+                .expect("parse_alter");
+        assert_eq!(first.alter_table(&alter_sql), Ok(0));
+
+        let (_, insert_sql) = engine_core::parse_insert("insert into Pet key 1 Dog { age: 3 }")
+            .expect("parse_insert");
+        crate::insert::insert(&db, &insert_sql).expect("insert");
+
+        assert_eq!(
+            first.commit(),
+            Err(EngineError::TransactionConflict {
+                key: "data_Pet_1".to_string()
+            })
+        );
+
+        let (_, select_sql) =
+            engine_core::parse_select("select Dog { age } from Pet").expect("parse_select");
+        assert_eq!(
+            crate::select::select(&db, select_sql).expect("select").rows,
+            vec![vec![ScalarValue::Int(3)]]
+        );
+    }
+
+    #[test]
+    fn test_drop_table_conflicts_with_a_concurrent_insert() {
+        let db = MemoryStorage::default();
+        let (_, table) = engine_core::parse_table("type User { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+        let user = TableName("User".to_string());
+
+        // the insert commits first, so the drop would leave it behind
+        let mut first = begin(&db);
+        assert_eq!(first.drop_table(&user), Ok(0));
+        crate::insert::insert(&db, &user_insert(1, 27)).expect("insert");
+        assert_eq!(
+            first.commit(),
+            Err(EngineError::TransactionConflict {
+                key: "data_User_1".to_string()
+            })
+        );
+
+        // the drop commits first, so the insert would be a row without a table
+        let mut second = begin(&db);
+        second.insert(&user_insert(2, 26)).expect("insert");
+        assert_eq!(crate::catalog::drop_table(&db, &user), Ok(1));
+        assert_eq!(
+            second.commit(),
+            Err(EngineError::TransactionConflict {
+                key: "table_User".to_string()
+            })
+        );
+        assert_eq!(db.scan("data_User_").count(), 0);
+    }
+
+    #[test]
+    fn test_update_conflicts_with_rows_changed_after_the_scan() {
+        let db = MemoryStorage::default();
+        let (_, table) = engine_core::parse_table("type User { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+        crate::insert::insert(&db, &user_insert(1, 27)).expect("insert");
+        crate::insert::insert(&db, &user_insert(2, 26)).expect("insert");
+
+        let mut first = begin(&db);
+        let (_, update_sql) = engine_core::parse_update("update User set age = 30 where age = 27")
+            .expect("parse_update");
+        assert_eq!(first.update(&update_sql), Ok(1));
+
+        // row 2 now matches the `where` that `first` already ran
+        let (_, other_update) =
+            engine_core::parse_update("update User set age = 27 where age = 26")
+                .expect("parse_update");
+        assert_eq!(crate::update::update(&db, &other_update), Ok(1));
+
+        assert_eq!(
+            first.commit(),
+            Err(EngineError::TransactionConflict {
+                key: "data_User_2".to_string()
+            })
+        );
+        assert_eq!(
+            select_ages(&db),
+            vec![vec![ScalarValue::Int(27)], vec![ScalarValue::Int(27)]]
+        );
+    }
+
+    #[test]
+    fn test_delete_conflicts_with_new_matching_rows() {
+        let db = MemoryStorage::default();
+        let (_, table) = engine_core::parse_table("type User { age: Int }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+        crate::insert::insert(&db, &user_insert(1, 27)).expect("insert");
+
+        let mut first = begin(&db);
+        let (_, delete_sql) =
+            engine_core::parse_delete("delete from User where age = 27").expect("parse_delete");
+        assert_eq!(first.delete(&delete_sql), Ok(1));
+
+        // a row `first` would have deleted, had it been there when it looked
+        crate::insert::insert(&db, &user_insert(2, 27)).expect("insert");
+
+        assert_eq!(
+            first.commit(),
+            Err(EngineError::TransactionConflict {
+                key: "data_User_2".to_string()
+            })
+        );
+        assert_eq!(
+            select_ages(&db),
+            vec![vec![ScalarValue::Int(27)], vec![ScalarValue::Int(27)]]
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use crate::data::{lookup_table_for_write, matching_rows};
+use crate::encoding::encode_row;
+use crate::storage::Storage;
+use crate::transaction::{autocommit, Transaction};
+use crate::EngineError;
+use engine_core::{Update, UpdateError};
+use std::collections::BTreeMap;
+
+/// typecheck and change every matching row, returning how many changed
+pub fn update(db: &impl Storage, update: &Update) -> Result<usize, EngineError> {
+    autocommit(db, |tx| update_in(tx, update))
+}
+
+pub(crate) fn update_in(
+    tx: &mut Transaction<impl Storage>,
+    update: &Update,
+) -> Result<usize, EngineError> {
+    let table = match lookup_table_for_write(tx, &update.table)? {
+        Some(table) => Ok(table),
+        None => Err(UpdateError::TableNotFound(update.table.clone())),
+    }?;
+
+    let mut tables = BTreeMap::new();
+    tables.insert(update.table.clone(), table);
+
+    engine_core::typecheck_update(&tables, update).map_err(UpdateError::TypeError)?;
+
+    let table = &tables[&update.table];
+    let rows = matching_rows(tx, table, &update.r#where)?;
+    let count = rows.len();
+    for (key, mut row) in rows {
+        for (column, value) in &update.values {
+            row.values.insert(column.clone(), value.clone());
+        }
+        let value = encode_row(table, &row).map_err(|reason| EngineError::CorruptRow {
+            key: String::from_utf8_lossy(&key).to_string(),
+            reason,
+        })?;
+        tx.put(&key, value)?;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod testing {
+    use super::update;
+    use crate::data::insert_table;
+    use crate::storage::MemoryStorage;
+    use crate::EngineError;
+    use engine_core::{
+        ColumnName, Location, ScalarValue, TableName, TypeError, TypeErrors, UpdateError,
+    };
+
+    #[test]
+    fn test_update() {
+        let db = MemoryStorage::default();
+        let (_, table) =
+            engine_core::parse_table("type User { age: Int, nice: Bool }").expect("parse_table");
+        insert_table(&db, &table).expect("insert_table");
+
+        for insert_sql in [
+            "insert into User key 1 { age: 27, nice: false }",
+            "insert into User key 2 { age: 100, nice: false }",
+        ] {
+            let (_, insert) = engine_core::parse_insert(insert_sql).expect("parse_insert");
+            crate::insert::insert(&db, &insert).expect("insert");
+        }
+
+        let (_, update_sql) =
+            engine_core::parse_update("update User set nice = true where age = 100")
+                .expect("parse_update");
+        assert_eq!(update(&db, &update_sql), Ok(1));
+
+        let (_, select_sql) = engine_core::parse_select("select age from User where nice = true")
+            .expect("parse_select");
+        assert_eq!(
+            crate::select::select(&db, select_sql).map(|result| result.rows),
+            Ok(vec![vec![ScalarValue::Int(100)]])
+        );
+
+        let (_, update_sql) =
+            engine_core::parse_update("update User set missing = 1").expect("parse_update");
+        assert_eq!(
+            update(&db, &update_sql),
+            Err(EngineError::Update(UpdateError::TypeError(TypeErrors(
+                vec![(
+                    Location::Column(ColumnName("missing".to_string())),
+                    TypeError::ColumnNotFound {
+                        table_name: TableName("User".to_string()),
+                        column_name: ColumnName("missing".to_string())
+                    }
+                )]
+            ))))
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! formatting files of statements without losing their comments
+use engine_core::{format_statement, parse_script, ParseError};
+
+/// statements longer than this are split over several lines
+pub const WIDTH: usize = 80;
+
+/// the script with every statement in its canonical form and ending in `;`.
+///
+/// comments between statements are kept, each on its own line unless it
+/// followed a statement on the same line, and runs of blank lines become one.
+/// a statement with a comment inside it is left as it is, as printing it again
+/// would lose the comment
+pub fn format_script(source: &str) -> Result<String, ParseError> {
+    let statements = parse_script(source)?;
+    let mut output = String::new();
+    let mut previous_end = 0;
+
+    for (span, statement, _) in &statements {
+        let newlines = write_comments(&mut output, &source[previous_end..span.start]);
+        start_line(&mut output, newlines);
+
+        let text = &source[span.start..span.end];
+        if has_comment(text) {
+            output.push_str(text);
+        } else {
+            output.push_str(&format_statement(statement, WIDTH));
+        }
+        output.push(';');
+        previous_end = span.end;
+    }
+    write_comments(&mut output, &source[previous_end..]);
+
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// whether the script is already formatted
+pub fn is_formatted(source: &str) -> Result<bool, ParseError> {
+    format_script(source).map(|formatted| formatted == source)
+}
+
+// a new line for the next thing, with a blank line before it if there was one
+fn start_line(output: &mut String, newlines: usize) {
+    if output.is_empty() {
+        return;
+    }
+    output.push('\n');
+    if newlines > 1 {
+        output.push('\n');
+    }
+}
+
+// write out the comments in the text between two statements, returning how
+// many newlines came after the last one
+fn write_comments(output: &mut String, gap: &str) -> usize {
+    let mut newlines = 0;
+    let mut rest = gap;
+
+    while let Some(next) = rest.chars().next() {
+        let comment_length = if rest.starts_with("--") || rest.starts_with('#') {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if rest.starts_with("/*") {
+            rest.find("*/").map_or(rest.len(), |end| end + 2)
+        } else {
+            if next == '\n' {
+                newlines += 1;
+            }
+            rest = &rest[next.len_utf8()..];
+            continue;
+        };
+
+        // a comment straight after a statement stays on the same line
+        if newlines == 0 && output.ends_with(';') {
+            output.push(' ');
+        } else {
+            start_line(output, newlines);
+        }
+        output.push_str(rest[..comment_length].trim_end());
+        rest = &rest[comment_length..];
+        newlines = 0;
+    }
+
+    newlines
+}
+
+// whether there is a comment anywhere outside of quotes
+fn has_comment(text: &str) -> bool {
+    let mut quote = None;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('"'), '\\') => {
+                chars.next();
+            }
+            (Some(open), c) if c == open => quote = None,
+            (None, '"' | '`') => quote = Some(c),
+            (None, '#') => return true,
+            (None, '-') if chars.peek() == Some(&'-') => return true,
+            (None, '/') if chars.peek() == Some(&'*') => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_script, has_comment, is_formatted};
+
+    #[test]
+    fn test_format_script() {
+        let source = "
+-- the users
+TYPE User   { name:String,age : Int };
+
+
+insert into User key 1 {name:\"Egg\", age: 27} # the first one
+;  SELECT name FROM User WHERE age=27
+/* the end */";
+
+        let formatted = "-- the users
+type User { age: Int, name: String };
+
+insert into User key 1 { age: 27, name: \"Egg\" }; # the first one
+select name from User where age = 27;
+/* the end */
+";
+
+        assert_eq!(format_script(source), Ok(formatted.to_string()));
+        assert_eq!(is_formatted(formatted), Ok(true));
+        assert_eq!(is_formatted(source), Ok(false));
+    }
+
+    #[test]
+    fn test_long_statements_are_wrapped() {
+        let source =
+            "type User { email: String, first_name: String, last_name: String, likes_dogs: Bool }";
+
+        assert_eq!(
+            format_script(source),
+            Ok("type User {
+  email: String,
+  first_name: String,
+  last_name: String,
+  likes_dogs: Bool
+};
+"
+            .to_string())
+        );
+    }
+
+    #[test]
+    fn test_comments_inside_statements_are_kept() {
+        let source = "select name -- just the name\n  from User;\n";
+
+        assert_eq!(format_script(source), Ok(source.to_string()));
+        assert!(has_comment(source));
+        assert!(!has_comment(
+            "select `a#b` from User where name = \"-- \\\" /*\""
+        ));
+    }
+
+    #[test]
+    fn test_empty_script() {
+        assert_eq!(format_script("  \n"), Ok(String::new()));
+    }
+}
@@ -0,0 +1,87 @@
+//! `oh-no-fmt [--check] [files...]`
+//!
+//! formats each file in place, or standard input to standard output when
+//! there are no files. with `--check` nothing is written, and it exits with
+//! an error if anything is not formatted
+use oh_no_fmt::format_script;
+use std::io::Read;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut check = false;
+    let mut paths = vec![];
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            "-h" | "--help" => {
+                println!("usage: oh-no-fmt [--check] [files...]");
+                return ExitCode::SUCCESS;
+            }
+            _ => paths.push(arg),
+        }
+    }
+
+    let results = if paths.is_empty() {
+        vec![format_stdin(check)]
+    } else {
+        // every file is checked, even once one is not formatted or can't be read
+        paths.iter().map(|path| format_file(path, check)).collect()
+    };
+
+    let mut success = true;
+    for result in results {
+        match result {
+            Ok(formatted) => success &= formatted,
+            Err(message) => {
+                eprintln!("{message}");
+                success = false;
+            }
+        }
+    }
+
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+// returns whether the file was already formatted
+fn format_file(path: &str, check: bool) -> Result<bool, String> {
+    let source = std::fs::read_to_string(path).map_err(|error| format!("{path}: {error}"))?;
+    let formatted = format(path, &source)?;
+    if formatted == source {
+        return Ok(true);
+    }
+
+    if check {
+        println!("{path} is not formatted");
+    } else {
+        std::fs::write(path, formatted).map_err(|error| format!("{path}: {error}"))?;
+    }
+    Ok(!check)
+}
+
+fn format_stdin(check: bool) -> Result<bool, String> {
+    let mut source = String::new();
+    std::io::stdin()
+        .read_to_string(&mut source)
+        .map_err(|error| format!("<stdin>: {error}"))?;
+    let formatted = format("<stdin>", &source)?;
+
+    if check {
+        Ok(formatted == source)
+    } else {
+        print!("{formatted}");
+        Ok(true)
+    }
+}
+
+fn format(path: &str, source: &str) -> Result<String, String> {
+    format_script(source).map_err(|error| {
+        format!(
+            "{path}:\n{}",
+            engine_core::render_parse_error(source, &error)
+        )
+    })
+}
@@ -0,0 +1,437 @@
+//! a postgres wire protocol front end, so `psql` and postgres drivers can
+//! run statements using simple queries.
+//!
+//! there are no users or passwords, and the extended query protocol that
+//! prepared statements use is answered with an error
+mod protocol;
+
+pub use protocol::Field;
+
+use engine::script::{Output, Session};
+use engine::storage::Storage;
+use engine::EngineError;
+use engine_core::{
+    parse_script, AlterError, CatalogCommand, CatalogError, ColumnName, DeleteError, InsertError,
+    ScalarType, ScalarValue, SelectError, SourceMap, Statement, TransactionCommand, Type,
+    TypeError, UpdateError,
+};
+use protocol::{read_message, read_startup, Messages, Startup, TransactionStatus};
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+
+const BOOL_OID: i32 = 16;
+const INT4_OID: i32 = 23;
+const TEXT_OID: i32 = 25;
+
+/// answer every connection on its own thread. a connection that can't be
+/// accepted is logged and skipped, so one bad client can't stop the server
+pub fn serve(listener: &TcpListener, db: &(impl Storage + Sync)) {
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    eprintln!("accepting a connection: {error}");
+                    continue;
+                }
+            };
+            // a connection that goes wrong only affects its own client
+            scope.spawn(move || {
+                let mut reader = &stream;
+                let mut writer = &stream;
+                let _ = connection(&mut reader, &mut writer, db);
+            });
+        }
+    });
+}
+
+/// one client session, from startup until it terminates or disconnects
+pub fn connection(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    db: &impl Storage,
+) -> io::Result<()> {
+    let mut messages = Messages::default();
+    // a transaction lasts from `begin` to `commit`, however many queries that takes
+    let mut session = Session::new(db);
+
+    loop {
+        match read_startup(reader)? {
+            Startup::Encryption => writer.write_all(b"N")?,
+            Startup::Cancel => return Ok(()),
+            Startup::Start => break,
+        }
+    }
+
+    messages.authentication_ok();
+    for (name, value) in [
+        ("server_version", "14"),
+        ("server_encoding", "UTF8"),
+        ("client_encoding", "UTF8"),
+        ("DateStyle", "ISO, MDY"),
+        ("integer_datetimes", "on"),
+        ("standard_conforming_strings", "on"),
+    ] {
+        messages.parameter_status(name, value);
+    }
+    messages.ready_for_query(TransactionStatus::Idle);
+    messages.flush(writer)?;
+
+    // after an extended query message fails, the rest are ignored until `Sync`
+    let mut skipping = false;
+    while let Some(message) = read_message(reader)? {
+        match message.tag {
+            b'Q' => {
+                let query = String::from_utf8_lossy(&message.body);
+                simple_query(&mut session, query.trim_end_matches('\0'), &mut messages);
+                messages.ready_for_query(status(&session));
+            }
+            b'X' => return Ok(()),
+            b'S' => {
+                skipping = false;
+                messages.ready_for_query(status(&session));
+            }
+            // `Flush` asks for nothing but what is already waiting
+            b'H' => {}
+            _ if skipping => {}
+            _ => {
+                messages.error("0A000", "only simple queries are supported", None);
+                skipping = true;
+            }
+        }
+        messages.flush(writer)?;
+    }
+    Ok(())
+}
+
+/// the fields for columns with these types, as the typechecker gives them
+pub fn describe(columns: &[(ColumnName, Type)]) -> Vec<Field> {
+    columns
+        .iter()
+        .map(|(column, r#type)| field(&column.0, r#type))
+        .collect()
+}
+
+fn field(name: &str, r#type: &Type) -> Field {
+    match r#type {
+        Type::ScalarType(scalar_type) => {
+            let (type_oid, type_size) = match scalar_type {
+                ScalarType::Bool => (BOOL_OID, 1),
+                ScalarType::Int => (INT4_OID, 4),
+                ScalarType::String => (TEXT_OID, -1),
+            };
+            Field {
+                name: name.to_string(),
+                type_oid,
+                type_size,
+                nullable: false,
+            }
+        }
+        Type::Optional(inner) => Field {
+            nullable: true,
+            ..field(name, inner)
+        },
+    }
+}
+
+// run each statement until one fails. unlike postgres, which runs them all in one
+// transaction, the ones before it stay committed unless there is a `begin`
+fn simple_query(session: &mut Session<impl Storage>, query: &str, messages: &mut Messages) {
+    let statements = match parse_script(query) {
+        Ok(statements) => statements,
+        Err(error) => {
+            let position = position(query, error.span.start);
+            messages.error("42601", &error.message, Some(position));
+            return;
+        }
+    };
+    if statements.is_empty() {
+        messages.empty_query();
+    }
+
+    for (_, statement, spans) in statements {
+        if let Err(error) = run(session, statement, messages) {
+            let (code, position) = error_details(query, &spans, &error);
+            messages.error(code, &error.to_string(), position);
+            return;
+        }
+    }
+}
+
+fn run(
+    session: &mut Session<impl Storage>,
+    statement: Statement,
+    messages: &mut Messages,
+) -> Result<(), EngineError> {
+    let command = match &statement {
+        Statement::Catalog(CatalogCommand::DropTable { .. }) => "DROP TABLE",
+        Statement::Select(_) | Statement::Catalog(_) => "SELECT",
+        Statement::Table(_) => "CREATE TABLE",
+        Statement::Insert(_) => "INSERT 0 1",
+        Statement::Update(_) => "UPDATE",
+        Statement::Delete(_) => "DELETE",
+        Statement::Alter(_) => "ALTER TABLE",
+        Statement::Transaction(command) => transaction_tag(*command),
+    };
+
+    match session.execute(statement)? {
+        Output::RowsAffected(count) if command == "UPDATE" || command == "DELETE" => {
+            messages.command_complete(&format!("{command} {count}"));
+        }
+        Output::TableCreated | Output::Inserted(_) | Output::RowsAffected(_) => {
+            messages.command_complete(command);
+        }
+        // a `commit` after a failed statement rolls back instead
+        Output::Transaction(done) => messages.command_complete(transaction_tag(done)),
+        Output::Rows(result) => {
+            messages.row_description(&describe(&result.schema));
+            for row in &result.rows {
+                let values: Vec<Option<String>> = row.iter().map(text_value).collect();
+                messages.data_row(&values);
+            }
+            messages.command_complete(&format!("SELECT {}", result.rows.len()));
+        }
+        Output::Tables(tables) => {
+            text_rows(messages, "table", tables.iter().map(ToString::to_string));
+        }
+        Output::Table(table) => text_rows(messages, "definition", [table.to_string()]),
+    }
+    Ok(())
+}
+
+fn transaction_tag(command: TransactionCommand) -> &'static str {
+    match command {
+        TransactionCommand::Begin => "BEGIN",
+        TransactionCommand::Commit => "COMMIT",
+        TransactionCommand::Rollback => "ROLLBACK",
+    }
+}
+
+fn status(session: &Session<impl Storage>) -> TransactionStatus {
+    if session.is_aborted() {
+        TransactionStatus::Failed
+    } else if session.in_transaction() {
+        TransactionStatus::InTransaction
+    } else {
+        TransactionStatus::Idle
+    }
+}
+
+// a single column of text
+fn text_rows(messages: &mut Messages, name: &str, rows: impl IntoIterator<Item = String>) {
+    messages.row_description(&[field(name, &Type::ScalarType(ScalarType::String))]);
+    let mut count = 0;
+    for row in rows {
+        messages.data_row(&[Some(row)]);
+        count += 1;
+    }
+    messages.command_complete(&format!("SELECT {count}"));
+}
+
+// how postgres writes each type as text
+fn text_value(value: &ScalarValue) -> Option<String> {
+    match value {
+        ScalarValue::Null => None,
+        ScalarValue::Bool(true) => Some("t".to_string()),
+        ScalarValue::Bool(false) => Some("f".to_string()),
+        ScalarValue::String(string) => Some(string.clone()),
+        ScalarValue::Int(int) => Some(int.to_string()),
+    }
+}
+
+// the `SQLSTATE` for an error, and where in the query it is if we know
+fn error_details(
+    query: &str,
+    spans: &SourceMap,
+    error: &EngineError,
+) -> (&'static str, Option<usize>) {
+    if let Some(type_errors) = error.type_errors() {
+        let Some((location, first)) = type_errors.0.first() else {
+            return ("42804", None);
+        };
+        let code = match first {
+            TypeError::TableNotFound(_) => "42P01",
+            TypeError::ColumnNotFound { .. } => "42703",
+            _ => "42804",
+        };
+        let position = spans
+            .span(location)
+            .map(|found| position(query, found.start));
+        return (code, position);
+    }
+
+    let code = match error {
+        EngineError::Insert(InsertError::DuplicateKey { .. }) => "23505",
+        EngineError::TransactionConflict { .. } => "40001",
+        EngineError::TransactionAborted => "25P02",
+        EngineError::Select(SelectError::TableNotFound(_))
+        | EngineError::Insert(InsertError::TableNotFound(_))
+        | EngineError::Update(UpdateError::TableNotFound(_))
+        | EngineError::Delete(DeleteError::TableNotFound(_))
+        | EngineError::Alter(AlterError::TableNotFound(_))
+        | EngineError::Catalog(CatalogError::TableNotFound(_)) => "42P01",
+        _ => "XX000",
+    };
+    (code, None)
+}
+
+// postgres counts characters from one
+fn position(query: &str, offset: usize) -> usize {
+    query[..offset].chars().count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{connection, describe, Field};
+    use engine::storage::MemoryStorage;
+    use engine_core::{ColumnName, ScalarType, Type};
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    // a message from the server, as its tag and body
+    fn read_message(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let mut header = [0; 5];
+        stream.read_exact(&mut header).expect("header");
+        let length = i32::from_be_bytes(header[1..].try_into().unwrap());
+        let mut body = vec![0; usize::try_from(length - 4).unwrap()];
+        stream.read_exact(&mut body).expect("body");
+        (header[0], body)
+    }
+
+    // every message up to and including `ReadyForQuery`
+    fn read_until_ready(stream: &mut TcpStream) -> Vec<(u8, Vec<u8>)> {
+        let mut messages = vec![];
+        loop {
+            let message = read_message(stream);
+            let ready = message.0 == b'Z';
+            messages.push(message);
+            if ready {
+                return messages;
+            }
+        }
+    }
+
+    fn query(stream: &mut TcpStream, query: &str) -> Vec<(u8, Vec<u8>)> {
+        let length = i32::try_from(query.len() + 5).unwrap();
+        stream.write_all(b"Q").unwrap();
+        stream.write_all(&length.to_be_bytes()).unwrap();
+        stream.write_all(query.as_bytes()).unwrap();
+        stream.write_all(&[0]).unwrap();
+        read_until_ready(stream)
+    }
+
+    #[test]
+    fn test_describe() {
+        let columns = [
+            (
+                ColumnName("name".to_string()),
+                Type::ScalarType(ScalarType::String),
+            ),
+            (
+                ColumnName("age".to_string()),
+                Type::Optional(Box::new(Type::ScalarType(ScalarType::Int))),
+            ),
+        ];
+
+        assert_eq!(
+            describe(&columns),
+            vec![
+                Field {
+                    name: "name".to_string(),
+                    type_oid: 25,
+                    type_size: -1,
+                    nullable: false
+                },
+                Field {
+                    name: "age".to_string(),
+                    type_oid: 23,
+                    type_size: 4,
+                    nullable: true
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_simple_queries() {
+        let db = MemoryStorage::default();
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let address = listener.local_addr().unwrap();
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let (stream, _) = listener.accept().expect("accept");
+                connection(&mut &stream, &mut &stream, &db).expect("connection");
+            });
+
+            let mut stream = TcpStream::connect(address).expect("connect");
+
+            // ask for ssl, which is refused
+            stream.write_all(&8_i32.to_be_bytes()).unwrap();
+            stream.write_all(&80_877_103_i32.to_be_bytes()).unwrap();
+            let mut answer = [0];
+            stream.read_exact(&mut answer).unwrap();
+            assert_eq!(&answer, b"N");
+
+            let parameters = b"user\0egg\0\0";
+            let length = i32::try_from(parameters.len() + 8).unwrap();
+            stream.write_all(&length.to_be_bytes()).unwrap();
+            stream.write_all(&196_608_i32.to_be_bytes()).unwrap();
+            stream.write_all(parameters).unwrap();
+            let startup = read_until_ready(&mut stream);
+            assert_eq!(startup[0], (b'R', vec![0, 0, 0, 0]));
+
+            let created = query(
+                &mut stream,
+                "type User { name: String, admin: Bool }; insert into User key 1 { name: \"Egg\", admin: true };",
+            );
+            assert_eq!(
+                created,
+                vec![
+                    (b'C', b"CREATE TABLE\0".to_vec()),
+                    (b'C', b"INSERT 0 1\0".to_vec()),
+                    (b'Z', b"I".to_vec()),
+                ]
+            );
+
+            let selected = query(&mut stream, "select name, admin from User");
+            assert_eq!(selected.len(), 4);
+            assert_eq!(selected[0].0, b'T');
+            assert_eq!(
+                selected[1],
+                (b'D', b"\0\x02\0\0\0\x03Egg\0\0\0\x01t".to_vec())
+            );
+            assert_eq!(selected[2], (b'C', b"SELECT 1\0".to_vec()));
+
+            let failed = query(&mut stream, "select age from User");
+            assert_eq!(failed[0].0, b'E');
+            let error = String::from_utf8_lossy(&failed[0].1).to_string();
+            assert!(error.contains("C42703\0"));
+            assert!(error.contains("P8\0"));
+
+            // after a failure the transaction only takes `commit` or `rollback`
+            let failed = query(
+                &mut stream,
+                "begin; insert into User key 1 { name: \"Horse\", admin: false }",
+            );
+            assert_eq!(failed[0], (b'C', b"BEGIN\0".to_vec()));
+            assert_eq!(failed[1].0, b'E');
+            assert_eq!(failed[2], (b'Z', b"E".to_vec()));
+            let rejected = query(&mut stream, "select name from User");
+            assert_eq!(rejected[0].0, b'E');
+            assert!(String::from_utf8_lossy(&rejected[0].1).contains("C25P02\0"));
+            assert_eq!(rejected[1], (b'Z', b"E".to_vec()));
+            assert_eq!(
+                query(&mut stream, "commit"),
+                vec![(b'C', b"ROLLBACK\0".to_vec()), (b'Z', b"I".to_vec())]
+            );
+
+            assert_eq!(
+                query(&mut stream, ""),
+                vec![(b'I', vec![]), (b'Z', b"I".to_vec())]
+            );
+
+            stream.write_all(b"X\0\0\0\x04").unwrap();
+        });
+    }
+}
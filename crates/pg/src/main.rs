@@ -0,0 +1,43 @@
+//! `oh-no-pg <path> [address]`
+//!
+//! serves the database at `path` to postgres clients, on `127.0.0.1:5432`
+//! unless an address is given
+use std::net::TcpListener;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: oh-no-pg <path> [address]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (path, address) = match args.as_slice() {
+        [flag] if flag == "-h" || flag == "--help" => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        [path] => (path.as_str(), "127.0.0.1:5432"),
+        [path, address] => (path.as_str(), address.as_str()),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let db = match rocksdb::DB::open_default(path) {
+        Ok(db) => db,
+        Err(error) => {
+            eprintln!("{path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let listener = match TcpListener::bind(address) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("{address}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!("listening on {address}");
+    oh_no_pg::serve(&listener, &db);
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,214 @@
+//! the parts of version 3 of the postgres wire protocol that simple queries use
+use std::io::{self, Read, Write};
+
+const PROTOCOL_VERSION: i32 = 196_608;
+const SSL_REQUEST: i32 = 80_877_103;
+const GSS_REQUEST: i32 = 80_877_104;
+const CANCEL_REQUEST: i32 = 80_877_102;
+
+// anything longer than this is not a message we want
+const MAX_LENGTH: usize = 1 << 24;
+
+/// the first thing a client sends
+pub(crate) enum Startup {
+    /// asks for an encrypted connection, which we answer with `N`
+    Encryption,
+    /// cancelling a query on another connection, which we cannot do
+    Cancel,
+    /// the start of a session
+    Start,
+}
+
+/// where the session is, as `ReadyForQuery` reports it
+#[derive(Clone, Copy)]
+pub(crate) enum TransactionStatus {
+    Idle,
+    InTransaction,
+    /// a statement failed, and everything until the transaction ends is rejected
+    Failed,
+}
+
+/// a message from the client once the session has started
+pub(crate) struct Message {
+    pub tag: u8,
+    pub body: Vec<u8>,
+}
+
+/// a column in the rows sent back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    /// the `oid` of the type in `pg_type`
+    pub type_oid: i32,
+    /// how many bytes the type takes, or `-1` if it varies
+    pub type_size: i16,
+    /// whether rows can have `NULL` in this column. `RowDescription` has no
+    /// place for this, so it is never sent and clients have to expect `NULL`
+    /// in any column
+    pub nullable: bool,
+}
+
+pub(crate) fn read_startup(stream: &mut impl Read) -> io::Result<Startup> {
+    let body = read_body(stream)?;
+    let Some((code, _parameters)) = body.split_first_chunk::<4>() else {
+        return Err(invalid("startup message is too short"));
+    };
+
+    // the parameters say who the user is, but anyone can connect
+    match i32::from_be_bytes(*code) {
+        SSL_REQUEST | GSS_REQUEST => Ok(Startup::Encryption),
+        CANCEL_REQUEST => Ok(Startup::Cancel),
+        PROTOCOL_VERSION => Ok(Startup::Start),
+        version => Err(invalid(&format!(
+            "unsupported protocol version {}.{}",
+            version >> 16,
+            version & 0xffff
+        ))),
+    }
+}
+
+/// the next message, or `None` if the client has gone
+pub(crate) fn read_message(stream: &mut impl Read) -> io::Result<Option<Message>> {
+    let mut tag = [0];
+    if stream.read(&mut tag)? == 0 {
+        return Ok(None);
+    }
+    let body = read_body(stream)?;
+    Ok(Some(Message { tag: tag[0], body }))
+}
+
+// a length, which counts itself, followed by that many bytes
+fn read_body(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut length = [0; 4];
+    stream.read_exact(&mut length)?;
+    let length = usize::try_from(i32::from_be_bytes(length))
+        .ok()
+        .and_then(|length| length.checked_sub(4))
+        .filter(|length| *length <= MAX_LENGTH)
+        .ok_or_else(|| invalid("invalid message length"))?;
+
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body)?;
+    Ok(body)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// messages waiting to be sent to the client
+#[derive(Debug, Default)]
+pub(crate) struct Messages(Vec<u8>);
+
+impl Messages {
+    pub fn flush(&mut self, stream: &mut impl Write) -> io::Result<()> {
+        stream.write_all(&self.0)?;
+        self.0.clear();
+        stream.flush()
+    }
+
+    pub fn authentication_ok(&mut self) {
+        self.message(b'R', &0_i32.to_be_bytes());
+    }
+
+    pub fn parameter_status(&mut self, name: &str, value: &str) {
+        let mut body = vec![];
+        put_string(&mut body, name);
+        put_string(&mut body, value);
+        self.message(b'S', &body);
+    }
+
+    pub fn ready_for_query(&mut self, status: TransactionStatus) {
+        let status = match status {
+            TransactionStatus::Idle => b"I",
+            TransactionStatus::InTransaction => b"T",
+            TransactionStatus::Failed => b"E",
+        };
+        self.message(b'Z', status);
+    }
+
+    pub fn row_description(&mut self, fields: &[Field]) {
+        let mut body = count(fields.len()).to_vec();
+        for field in fields {
+            put_string(&mut body, &field.name);
+            // not from a table, so no table oid or column number
+            body.extend(0_i32.to_be_bytes());
+            body.extend(0_i16.to_be_bytes());
+            body.extend(field.type_oid.to_be_bytes());
+            body.extend(field.type_size.to_be_bytes());
+            // no type modifier, and values are sent as text
+            body.extend((-1_i32).to_be_bytes());
+            body.extend(0_i16.to_be_bytes());
+        }
+        self.message(b'T', &body);
+    }
+
+    /// a row of values in their text format, `None` being `NULL`
+    pub fn data_row(&mut self, values: &[Option<String>]) {
+        let mut body = count(values.len()).to_vec();
+        for value in values {
+            match value {
+                Some(value) => {
+                    body.extend(length(value.len()));
+                    body.extend(value.as_bytes());
+                }
+                None => body.extend((-1_i32).to_be_bytes()),
+            }
+        }
+        self.message(b'D', &body);
+    }
+
+    pub fn command_complete(&mut self, tag: &str) {
+        let mut body = vec![];
+        put_string(&mut body, tag);
+        self.message(b'C', &body);
+    }
+
+    pub fn empty_query(&mut self) {
+        self.message(b'I', &[]);
+    }
+
+    /// `position` counts characters into the query, starting from one
+    pub fn error(&mut self, code: &str, message: &str, position: Option<usize>) {
+        let mut body = vec![];
+        for (field, value) in [
+            (b'S', "ERROR"),
+            (b'V', "ERROR"),
+            (b'C', code),
+            (b'M', message),
+        ] {
+            body.push(field);
+            put_string(&mut body, value);
+        }
+        if let Some(position) = position {
+            body.push(b'P');
+            put_string(&mut body, &position.to_string());
+        }
+        body.push(0);
+        self.message(b'E', &body);
+    }
+
+    fn message(&mut self, tag: u8, body: &[u8]) {
+        self.0.push(tag);
+        self.0.extend(length(body.len() + 4));
+        self.0.extend(body);
+    }
+}
+
+fn put_string(body: &mut Vec<u8>, string: &str) {
+    // strings are null terminated, so cannot contain a null themselves
+    body.extend(string.bytes().filter(|byte| *byte != 0));
+    body.push(0);
+}
+
+fn length(length: usize) -> [u8; 4] {
+    i32::try_from(length)
+        .expect("message is too long")
+        .to_be_bytes()
+}
+
+fn count(count: usize) -> [u8; 2] {
+    i16::try_from(count)
+        .expect("too many values in a row")
+        .to_be_bytes()
+}
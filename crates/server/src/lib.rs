@@ -0,0 +1,336 @@
+//! answering queries and inserts over HTTP, with JSON going both ways
+//!
+//! `POST /query` takes the text of a `select`, and responds with the type of
+//! each column and the rows in that order. nothing else can be run through it,
+//! so the server can't change tables or delete rows. `POST /insert` takes an
+//! `InsertRequest` and responds with the key.
+//! failures respond with `{ "error": kind, "message": ... }`, plus where the
+//! problems are for parse and type errors
+use engine::select::ResultSet;
+use engine::storage::Storage;
+use engine::{to_serde_json, EngineError};
+use engine_core::{
+    parse_statement, AlterError, CatalogError, ColumnName, DeleteError, Insert, InsertError,
+    InsertValue, OnConflict, ParseError, SelectError, Statement, TableName, Type, UpdateError,
+};
+use serde_derive::Deserialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Server};
+
+/// a status code and the JSON to send with it
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+/// the body of `POST /insert`
+#[derive(Debug, Deserialize)]
+pub struct InsertRequest {
+    pub table: TableName,
+    /// left out when the table generates its own keys
+    #[serde(default)]
+    pub key: Option<i32>,
+    pub value: InsertValue,
+    /// replace any row that already has the key, rather than failing
+    #[serde(default)]
+    pub replace: bool,
+}
+
+/// answer requests one at a time, until the server is unblocked
+pub fn serve(server: &Server, db: &impl Storage) {
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let response = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => handle(db, request.method(), request.url(), &body),
+            Err(error) => failure(400, "invalid_request", &error),
+        };
+
+        let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+        // nothing to be done if the client has gone away
+        let _ = request.respond(
+            tiny_http::Response::from_string(response.body.to_string())
+                .with_status_code(response.status)
+                .with_header(content_type),
+        );
+    }
+}
+
+pub fn handle(db: &impl Storage, method: &Method, url: &str, body: &str) -> Response {
+    match (method, url) {
+        (Method::Post, "/query") => query(db, body),
+        (Method::Post, "/insert") => insert(db, body),
+        (_, "/query" | "/insert") => failure(
+            405,
+            "method_not_allowed",
+            &format!("{url} only accepts POST"),
+        ),
+        _ => failure(404, "not_found", &format!("nothing at {url}")),
+    }
+}
+
+fn query(db: &impl Storage, source: &str) -> Response {
+    let statement = match parse_statement(source) {
+        Ok(statement) => statement,
+        Err(error) => return parse_failure(&error),
+    };
+    let Statement::Select(select) = statement else {
+        return failure(400, "not_a_select", &"`/query` only runs `select`");
+    };
+
+    match engine::select::select(db, select) {
+        Ok(result) => Response {
+            status: 200,
+            body: rows_json(&result),
+        },
+        Err(error) => engine_failure(&error),
+    }
+}
+
+fn insert(db: &impl Storage, body: &str) -> Response {
+    let request: InsertRequest = match serde_json::from_str(body) {
+        Ok(request) => request,
+        Err(error) => return failure(400, "invalid_request", &error),
+    };
+
+    let insert = Insert {
+        table: request.table,
+        key: request.key,
+        value: request.value,
+        on_conflict: if request.replace {
+            OnConflict::Replace
+        } else {
+            OnConflict::Fail
+        },
+        returning_key: true,
+    };
+
+    match engine::insert::insert(db, &insert) {
+        Ok(key) => Response {
+            status: 200,
+            body: json!({ "key": key }),
+        },
+        Err(error) => engine_failure(&error),
+    }
+}
+
+// the columns with their types, then each row as values in the same order
+fn rows_json(result: &ResultSet) -> Value {
+    let columns: Vec<Value> = result
+        .schema
+        .iter()
+        .map(|(column, r#type)| column_json(column, r#type))
+        .collect();
+    let rows: Vec<Value> = result
+        .rows
+        .iter()
+        .map(|row| row.iter().map(to_serde_json).collect())
+        .collect();
+
+    json!({ "columns": columns, "rows": rows })
+}
+
+fn column_json(column: &ColumnName, r#type: &Type) -> Value {
+    match r#type {
+        Type::ScalarType(scalar_type) => json!({
+            "name": column.0,
+            "type": scalar_type.to_string(),
+            "nullable": false
+        }),
+        Type::Optional(inner) => {
+            let mut column = column_json(column, inner);
+            column["nullable"] = Value::Bool(true);
+            column
+        }
+    }
+}
+
+fn failure(status: u16, kind: &str, message: &impl ToString) -> Response {
+    Response {
+        status,
+        body: json!({ "error": kind, "message": message.to_string() }),
+    }
+}
+
+fn parse_failure(error: &ParseError) -> Response {
+    let mut response = failure(400, "parse_error", &error.message);
+    response.body["span"] = json!({ "start": error.span.start, "end": error.span.end });
+    response
+}
+
+fn engine_failure(error: &EngineError) -> Response {
+    if let Some(type_errors) = error.type_errors() {
+        let mut response = failure(400, "type_error", &error);
+        response.body["errors"] = type_errors
+            .0
+            .iter()
+            .map(|(location, error)| {
+                json!({ "location": location.to_string(), "message": error.to_string() })
+            })
+            .collect();
+        return response;
+    }
+
+    let (status, kind) = match error {
+        EngineError::Select(SelectError::TableNotFound(_))
+        | EngineError::Insert(InsertError::TableNotFound(_))
+        | EngineError::Update(UpdateError::TableNotFound(_))
+        | EngineError::Delete(DeleteError::TableNotFound(_))
+        | EngineError::Alter(AlterError::TableNotFound(_))
+        | EngineError::Catalog(CatalogError::TableNotFound(_)) => (404, "table_not_found"),
+        EngineError::Insert(InsertError::DuplicateKey { .. }) => (409, "duplicate_key"),
+        EngineError::TransactionConflict { .. } => (409, "conflict"),
+        EngineError::Catalog(CatalogError::IncompatibleRedefinition { .. }) => {
+            (409, "incompatible_redefinition")
+        }
+        _ => (500, "internal_error"),
+    };
+    failure(status, kind, &error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{handle, serve, Response};
+    use engine::script::execute_script;
+    use engine::storage::MemoryStorage;
+    use serde_json::json;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use tiny_http::{Method, Server};
+
+    fn post(db: &MemoryStorage, url: &str, body: &str) -> Response {
+        handle(db, &Method::Post, url, body)
+    }
+
+    #[test]
+    fn test_query() {
+        let db = MemoryStorage::default();
+        execute_script(
+            &db,
+            "type User { name: String, age: Int };
+            insert into User key 1 { name: \"Egg\", age: 27 };",
+        )
+        .expect("execute_script");
+
+        assert_eq!(
+            post(&db, "/query", "select name, age from User;"),
+            Response {
+                status: 200,
+                body: json!({
+                    "columns": [
+                        { "name": "name", "type": "String", "nullable": false },
+                        { "name": "age", "type": "Int", "nullable": false }
+                    ],
+                    "rows": [["Egg", 27]]
+                })
+            }
+        );
+        assert_eq!(
+            post(&db, "/query", "delete from User where age = 27"),
+            Response {
+                status: 400,
+                body: json!({
+                    "error": "not_a_select",
+                    "message": "`/query` only runs `select`"
+                })
+            }
+        );
+        assert_eq!(post(&db, "/query", "drop type User").status, 400);
+        assert_eq!(post(&db, "/query", "select age from User").status, 200);
+    }
+
+    #[test]
+    fn test_insert() {
+        let db = MemoryStorage::default();
+        execute_script(&db, "type User { name: String }").expect("execute_script");
+
+        let body = r#"{ "table": "User", "key": 1, "value": { "values": { "name": { "String": "Egg" } } } }"#;
+        assert_eq!(
+            post(&db, "/insert", body),
+            Response {
+                status: 200,
+                body: json!({ "key": 1 })
+            }
+        );
+        assert_eq!(
+            post(&db, "/insert", body),
+            Response {
+                status: 409,
+                body: json!({
+                    "error": "duplicate_key",
+                    "message": "key 1 already exists in table User"
+                })
+            }
+        );
+        assert_eq!(post(&db, "/insert", "{}").status, 400);
+    }
+
+    #[test]
+    fn test_errors() {
+        let db = MemoryStorage::default();
+        execute_script(&db, "type User { name: String }").expect("execute_script");
+
+        assert_eq!(
+            post(&db, "/query", "select name User"),
+            Response {
+                status: 400,
+                body: json!({
+                    "error": "parse_error",
+                    "message": "expected `from` after column list",
+                    "span": { "start": 12, "end": 16 }
+                })
+            }
+        );
+        assert_eq!(
+            post(&db, "/query", "select age from User"),
+            Response {
+                status: 400,
+                body: json!({
+                    "error": "type_error",
+                    "message": "column age: column age not found in table User",
+                    "errors": [{
+                        "location": "column age",
+                        "message": "column age not found in table User"
+                    }]
+                })
+            }
+        );
+        assert_eq!(post(&db, "/query", "select a from Horse").status, 404);
+        assert_eq!(handle(&db, &Method::Get, "/query", "").status, 405);
+        assert_eq!(post(&db, "/horse", "").status, 404);
+    }
+
+    #[test]
+    fn test_serve_on_localhost() {
+        let db = MemoryStorage::default();
+        execute_script(&db, "type User { name: String }").expect("execute_script");
+        let server = Server::http("127.0.0.1:0").expect("Server::http");
+        let address = server.server_addr().to_ip().expect("an ip address");
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| serve(&server, &db));
+
+            let request = |body: &str| {
+                let mut stream = TcpStream::connect(address).expect("connect");
+                write!(
+                    stream,
+                    "POST /query HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{body}",
+                    body.len()
+                )
+                .expect("write");
+                let mut response = String::new();
+                stream.read_to_string(&mut response).expect("read");
+                response
+            };
+
+            let response = request("select name from User");
+            assert!(response.starts_with("HTTP/1.1 200"));
+            assert!(response.contains("Content-Type: application/json"));
+            assert!(response.ends_with(
+                r#"{"columns":[{"name":"name","nullable":false,"type":"String"}],"rows":[]}"#
+            ));
+
+            server.unblock();
+        });
+    }
+}
@@ -0,0 +1,42 @@
+//! `oh-no-server <path> [address]`
+//!
+//! serves the database at `path` over HTTP, on `127.0.0.1:8080` unless an
+//! address is given
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: oh-no-server <path> [address]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (path, address) = match args.as_slice() {
+        [flag] if flag == "-h" || flag == "--help" => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        [path] => (path.as_str(), "127.0.0.1:8080"),
+        [path, address] => (path.as_str(), address.as_str()),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let db = match rocksdb::DB::open_default(path) {
+        Ok(db) => db,
+        Err(error) => {
+            eprintln!("{path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let server = match tiny_http::Server::http(address) {
+        Ok(server) => server,
+        Err(error) => {
+            eprintln!("{address}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!("listening on http://{}", server.server_addr());
+    oh_no_server::serve(&server, &db);
+    ExitCode::SUCCESS
+}